@@ -1,5 +1,5 @@
 use crate::ICode::*;
-use iparse::error::ParserError;
+use iparse::error::{DebugWidth, ParserError};
 use iparse::span::span_union;
 use iparse::test::{test_parse, Trace};
 use iparse::tracer::CTracer;
@@ -294,6 +294,8 @@ impl<'s> Parser<'s, NonTerminal1<'s>, ICode> for ParseNonTerminal1 {
         trace: &'t mut impl Tracer<'s, ICode>,
         rest: Span<'s>,
     ) -> IParserResult<'s, NonTerminal1<'s>> {
+        trace.enter(Self::id(), rest);
+
         let (rest, a) = ParseTerminalA::parse(trace, rest).track(trace)?;
         let (rest, b) = ParseTerminalB::parse(trace, rest).track(trace)?;
 
@@ -345,6 +347,8 @@ impl<'s> Parser<'s, (), ICode> for ParseNonTerminal3 {
     }
 
     fn parse<'t>(trace: &'t mut impl Tracer<'s, ICode>, rest: Span<'s>) -> IParserResult<'s, ()> {
+        trace.enter(Self::id(), rest);
+
         let mut loop_rest = rest;
         loop {
             let rest2 = loop_rest;
@@ -388,7 +392,7 @@ fn main() {
     test_nonterminal2();
 }
 
-const R: Trace = Trace;
+const R: Trace = Trace(DebugWidth::Medium);
 
 // #[test]
 pub fn test_terminal_a() {
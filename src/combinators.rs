@@ -0,0 +1,353 @@
+//!
+//! Combinators that work on the results of a Parser.
+//!
+
+use crate::error::ParserError;
+use crate::{Code, IntoParserError, ParserNomResult, ParserResult, Span, Tracer};
+use memchr::memmem;
+use nom::error::ErrorKind;
+use nom::{IResult, InputTake, Offset, Slice};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Transforms the output of a parser with a pure function.
+/// Errors are passed through untouched.
+pub fn map_parse<'s, C: Code, O, O2>(
+    result: ParserResult<'s, C, (Span<'s>, O)>,
+    f: impl FnOnce(O) -> O2,
+) -> ParserResult<'s, C, (Span<'s>, O2)> {
+    match result {
+        Ok((rest, tok)) => Ok((rest, f(tok))),
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks the output of a parser with a predicate.
+/// If the predicate fails the Ok is converted to an error with the given code.
+///
+/// input is the span the parser was called with. The span of the error is
+/// the parsed token, from input up to the rest.
+pub fn verify_parse<'s, C: Code, O>(
+    input: Span<'s>,
+    result: ParserResult<'s, C, (Span<'s>, O)>,
+    code: C,
+    pred: impl Fn(&O) -> bool,
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    match result {
+        Ok((rest, tok)) => {
+            if pred(&tok) {
+                Ok((rest, tok))
+            } else {
+                Err(ParserError::new(code, input.take(input.offset(&rest))))
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs the parser fn without consuming the input.
+/// On success the original rest is returned together with the value.
+/// Errors are returned as the inner parser produced them.
+pub fn peek_parse<'s, 't, C: Code, O, T: Tracer<'s, C>>(
+    trace: &'t mut T,
+    rest: Span<'s>,
+    f: impl FnOnce(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>,
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    match f(trace, rest) {
+        Ok((_, tok)) => Ok((rest, tok)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Commits to the current branch like nom's cut.
+/// An error is marked as failure, so alternatives should not be tried any
+/// longer. into_nom() turns it into a nom::Err::Failure.
+pub fn cut_parse<'s, C: Code, O>(
+    result: ParserResult<'s, C, (Span<'s>, O)>,
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    match result {
+        Ok(v) => Ok(v),
+        Err(e) => Err(e.into_failure()),
+    }
+}
+
+/// Error recovery. Skips the input up to and including the next delimiter.
+///
+/// The error is stashed with the current stack frame, so this must be called
+/// after trace.enter(). If the delimiter is not found, everything up to the
+/// end of the input is skipped.
+///
+/// Returns the rest after the delimiter and the original error.
+pub fn recover_until<'s, C: Code>(
+    trace: &mut impl Tracer<'s, C>,
+    rest: Span<'s>,
+    err: ParserError<'s, C>,
+    delim: char,
+) -> (Span<'s>, ParserError<'s, C>) {
+    let rest = skip_until(trace, rest, delim);
+
+    trace.stash(err.clone());
+
+    (rest, err)
+}
+
+// Skips up to and including the next delimiter, or to the end of the input.
+fn skip_until<'s, C: Code>(
+    trace: &mut impl Tracer<'s, C>,
+    rest: Span<'s>,
+    delim: char,
+) -> Span<'s> {
+    let mut buf = [0u8; 4];
+    let delim = delim.encode_utf8(&mut buf).as_bytes();
+
+    let rest = match memmem::find(rest.fragment().as_bytes(), delim) {
+        None => rest.slice(rest.len()..),
+        Some(offset) => rest.slice(offset + delim.len()..),
+    };
+    trace.step("recover", rest);
+
+    rest
+}
+
+/// Parses all items up to the end of the input, and doesn't stop at the
+/// first error.
+///
+/// The items are separated by the delimiter. A delimiter directly after an
+/// item is skipped. On an error the input is skipped up to the next delimiter
+/// the same way as recover_until() does, and parsing continues.
+///
+/// This must be called after trace.enter(). An item that consumes nothing
+/// is reported with the code of the current parser function.
+///
+/// Returns the parsed items and the errors, both in source order. The errors
+/// are not stashed with the tracer, they are only returned here.
+pub fn parse_all<'s, C: Code, O, T: Tracer<'s, C>>(
+    trace: &mut T,
+    rest: Span<'s>,
+    item_fn: impl Fn(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>,
+    delim: char,
+) -> (Vec<O>, Vec<ParserError<'s, C>>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut loop_rest = rest;
+    while !loop_rest.is_empty() {
+        let rest2 = loop_rest;
+
+        let rest2 = match item_fn(trace, rest2) {
+            Ok((rest3, _)) if rest3 == rest2 => {
+                // no progress
+                let code = trace.current().unwrap_or(C::NOM_ERROR);
+                errors.push(ParserError::new(code, rest3));
+                skip_until(trace, rest3, delim)
+            }
+            Ok((rest3, item)) => {
+                items.push(item);
+                if rest3.fragment().starts_with(delim) {
+                    rest3.slice(delim.len_utf8()..)
+                } else {
+                    rest3
+                }
+            }
+            Err(err) => {
+                errors.push(err);
+                skip_until(trace, rest2, delim)
+            }
+        };
+
+        loop_rest = rest2;
+    }
+
+    (items, errors)
+}
+
+/// Runs a nom parser with tracing.
+///
+/// Enters a stack frame for the code, and maps any error to the code
+/// the same way as TrackParseResult::track_as() does.
+pub fn trace_nom<'s, C: Code, O>(
+    trace: &mut impl Tracer<'s, C>,
+    rest: Span<'s>,
+    code: C,
+    f: impl Fn(Span<'s>) -> IResult<Span<'s>, O, ParserError<'s, C>>,
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    trace.enter(code, rest);
+
+    match f(rest) {
+        Ok((rest2, tok)) => {
+            let span = rest.take(rest.offset(&rest2));
+            trace.ok(rest2, span, tok)
+        }
+        Err(e) => {
+            let pe: ParserError<'s, C> = e.into();
+            trace.err(pe.into_code(code))
+        }
+    }
+}
+
+/// Runs a nom parser that only recognizes a token and returns the token span.
+///
+/// No stack frame is entered, a match is recorded as step "token".
+/// The error gets the given code, use track() for it like with any
+/// other ParserResult.
+pub fn span_parser<'s, C: Code>(
+    trace: &mut impl Tracer<'s, C>,
+    rest: Span<'s>,
+    code: C,
+    f: impl Fn(Span<'s>) -> ParserNomResult<'s, C>,
+) -> ParserResult<'s, C, (Span<'s>, Span<'s>)> {
+    match f(rest) {
+        Ok((rest, tok)) => {
+            trace.step("token", tok);
+            Ok((rest, tok))
+        }
+        Err(e) => Err(e.into_with_code(code)),
+    }
+}
+
+/// Parser fn for alt_parse.
+pub type AltFn<'s, T, C, O> = fn(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>;
+
+/// Tries the alternatives in order and returns the first success.
+///
+/// The winning alternative is noted in the trace as "alt -> code".
+/// A failure (see cut_parse) ends the search immediately. If all
+/// alternatives fail, their errors are stashed and an error with the
+/// given code is returned.
+///
+/// No stack frame is entered, use track() for the result.
+pub fn alt_parse<'s, C: Code, O, T: Tracer<'s, C>>(
+    trace: &mut T,
+    rest: Span<'s>,
+    code: C,
+    alts: &[(C, AltFn<'s, T, C, O>)],
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    for (alt, f) in alts {
+        match f(trace, rest) {
+            Ok(v) => {
+                trace.debug(format!("alt -> {}", alt));
+                return Ok(v);
+            }
+            Err(e) if e.is_failure() => return Err(e),
+            Err(e) => trace.stash(e),
+        }
+    }
+    Err(ParserError::new(code, rest))
+}
+
+/// Parses items repeatedly and folds them into the accumulator,
+/// like nom's fold_many0. No Vec is needed for the items.
+///
+/// Stops at the first error of item_fn, a failure (see cut_parse) is
+/// returned. An item that consumes nothing is an error with NOM_ERROR
+/// and ErrorKind::Many0, as the loop would never end.
+///
+/// Returns the accumulator and the span of all consumed items.
+///
+/// No stack frame is entered, use track() for the result.
+pub fn fold_many0_parse<'s, C: Code, O, A, T: Tracer<'s, C>>(
+    trace: &mut T,
+    rest: Span<'s>,
+    item_fn: impl Fn(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>,
+    init: A,
+    f: impl Fn(A, O) -> A,
+) -> ParserResult<'s, C, (Span<'s>, (Span<'s>, A))> {
+    let start = rest;
+
+    let mut acc = init;
+    let mut loop_rest = rest;
+    loop {
+        match item_fn(trace, loop_rest) {
+            Ok((rest2, _)) if rest2.location_offset() == loop_rest.location_offset() => {
+                return Err(ParserError::new_with_nom(
+                    C::NOM_ERROR,
+                    ErrorKind::Many0,
+                    loop_rest,
+                ));
+            }
+            Ok((rest2, item)) => {
+                acc = f(acc, item);
+                loop_rest = rest2;
+            }
+            Err(e) if e.is_failure() => return Err(e),
+            Err(_) => break,
+        }
+    }
+
+    let span = start.take(start.offset(&loop_rest));
+    Ok((loop_rest, (span, acc)))
+}
+
+/// Parses open, inner and close like nom's delimited.
+///
+/// Returns the inner value and the span from open to close.
+/// Errors of open and inner are passed through. If close is missing the
+/// error has the given code at the rest after inner, and the code is added
+/// as suggestion, so the closing delimiter can be inserted.
+///
+/// No stack frame is entered, use track() for the result.
+pub fn delimited_parse<'s, C: Code, O, T: Tracer<'s, C>>(
+    trace: &mut T,
+    rest: Span<'s>,
+    code: C,
+    open_fn: impl Fn(Span<'s>) -> ParserNomResult<'s, C>,
+    inner_fn: impl FnOnce(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>,
+    close_fn: impl Fn(Span<'s>) -> ParserNomResult<'s, C>,
+) -> ParserResult<'s, C, (Span<'s>, (Span<'s>, O))> {
+    let start = rest;
+
+    let (rest, open) = open_fn(rest)?;
+    trace.step("open", open);
+
+    let (rest, val) = inner_fn(trace, rest)?;
+
+    match close_fn(rest) {
+        Ok((rest, close)) => {
+            trace.step("close", close);
+            let span = start.take(start.offset(&rest));
+            Ok((rest, (span, val)))
+        }
+        Err(_) => {
+            let mut err = ParserError::new(code, rest);
+            err.add_suggest(code, rest);
+            Err(err)
+        }
+    }
+}
+
+/// Runs the parser fn and catches any panic, e.g. from span_union() with
+/// spans of different inputs. Meant as entry point for fuzzing.
+///
+/// A panic is returned as error with NOM_FAILURE at the start of the input.
+/// The open stack frames of the tracer are finished with this error, so
+/// this must be called with no frame entered.
+pub fn parse_checked<'s, C: Code, O, T: Tracer<'s, C>>(
+    trace: &mut T,
+    rest: Span<'s>,
+    f: impl FnOnce(&mut T, Span<'s>) -> ParserResult<'s, C, (Span<'s>, O)>,
+) -> ParserResult<'s, C, (Span<'s>, O)> {
+    debug_assert!(
+        trace.current().is_none(),
+        "parse_checked() must be called without an open stack frame"
+    );
+
+    match panic::catch_unwind(AssertUnwindSafe(|| f(trace, rest))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let err = ParserError::new(C::NOM_FAILURE, rest).into_failure();
+            if trace.current().is_some() {
+                let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+                    msg.to_string()
+                } else if let Some(msg) = payload.downcast_ref::<String>() {
+                    msg.clone()
+                } else {
+                    "panic".to_string()
+                };
+                trace.debug(format!("panic: {}", msg));
+            }
+            while trace.current().is_some() {
+                let _ = trace.err::<()>(err.clone());
+            }
+            Err(err)
+        }
+    }
+}
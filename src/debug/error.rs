@@ -9,8 +9,8 @@ impl<'s, C: Code> Debug for ParserError<'s, C> {
         match f.width() {
             None | Some(0) => debug_parse_of_error_short(f, self),
             Some(1) => debug_parse_of_error_medium(f, self),
-            Some(2) => debug_parse_of_error_long(f, self),
-            _ => Ok(()),
+            Some(2) => debug_parse_of_error_long(f, DebugWidth::Long, self),
+            Some(n) => debug_parse_of_error_long(f, DebugWidth::Custom(n), self),
         }
     }
 }
@@ -97,28 +97,7 @@ fn debug_parse_of_error_medium<'s, C: Code>(
 
     let expect = err.expect_as_ref();
     if !expect.is_empty() {
-        let mut sorted = expect.clone();
-        sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
-
-        // per offset
-        let mut grp_offset = 0;
-        let mut grp = Vec::new();
-        let mut subgrp = Vec::new();
-        for exp in &sorted {
-            if exp.span.location_offset() != grp_offset {
-                if !subgrp.is_empty() {
-                    grp.push((grp_offset, subgrp));
-                    subgrp = Vec::new();
-                }
-                grp_offset = exp.span.location_offset();
-            }
-
-            subgrp.push(*exp);
-        }
-        if !subgrp.is_empty() {
-            grp.push((grp_offset, subgrp));
-        }
+        let grp = Expect::group_by_offset(expect);
 
         for (g_off, subgrp) in grp {
             let first = subgrp.first().unwrap();
@@ -134,28 +113,7 @@ fn debug_parse_of_error_medium<'s, C: Code>(
 
     let suggest = err.suggest_as_ref();
     if !suggest.is_empty() {
-        let mut sorted = suggest.clone();
-        sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
-
-        // per offset
-        let mut grp_offset = 0;
-        let mut grp = Vec::new();
-        let mut subgrp = Vec::new();
-        for exp in &sorted {
-            if exp.span.location_offset() != grp_offset {
-                if !subgrp.is_empty() {
-                    grp.push((grp_offset, subgrp));
-                    subgrp = Vec::new();
-                }
-                grp_offset = exp.span.location_offset();
-            }
-
-            subgrp.push(*exp);
-        }
-        if !subgrp.is_empty() {
-            grp.push((grp_offset, subgrp));
-        }
+        let grp = Suggest::group_by_offset(suggest);
 
         for (g_off, subgrp) in grp {
             let first = subgrp.first().unwrap();
@@ -174,21 +132,17 @@ fn debug_parse_of_error_medium<'s, C: Code>(
 
 fn debug_parse_of_error_long<'s, C: Code>(
     f: &mut impl fmt::Write,
+    w: DebugWidth,
     err: &ParserError<'s, C>,
 ) -> fmt::Result {
-    writeln!(
-        f,
-        "ParserError {} \"{}\"",
-        err.code,
-        restrict(DebugWidth::Long, err.span)
-    )?;
+    writeln!(f, "ParserError {} \"{}\"", err.code, restrict(w, err.span))?;
 
     let nom = err.nom();
     if !nom.is_empty() {
         writeln!(f, "nom=")?;
         for n in &nom {
             indent(f, 1)?;
-            writeln!(f, "{:?}:\"{}\"", n.kind, restrict(DebugWidth::Long, n.span))?;
+            writeln!(f, "{:?}:\"{}\"", n.kind, restrict(w, n.span))?;
         }
     }
 
@@ -198,13 +152,27 @@ fn debug_parse_of_error_long<'s, C: Code>(
         sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
 
         writeln!(f, "expect=")?;
-        debug_expect2_long(f, &sorted, 1)?;
+        debug_expect2_long(f, w, &sorted, 1)?;
     }
 
     let suggest = err.suggest_as_ref();
     if !suggest.is_empty() {
         writeln!(f, "suggest=")?;
-        debug_suggest2_long(f, &suggest, 1)?;
+        debug_suggest2_long(f, w, &suggest, 1)?;
+    }
+
+    let trivia = err.trivia();
+    if !trivia.is_empty() {
+        writeln!(f, "trivia=")?;
+        for t in &trivia {
+            indent(f, 1)?;
+            writeln!(
+                f,
+                "{}:\"{}\"",
+                t.span.location_offset(),
+                restrict(w, t.span)
+            )?;
+        }
     }
 
     Ok(())
@@ -219,6 +187,7 @@ fn indent(f: &mut impl fmt::Write, ind: usize) -> fmt::Result {
 
 fn debug_expect2_long<C: Code>(
     f: &mut impl fmt::Write,
+    w: DebugWidth,
     exp_vec: &Vec<&Expect<'_, C>>,
     ind: usize,
 ) -> fmt::Result {
@@ -229,8 +198,11 @@ fn debug_expect2_long<C: Code>(
             "{}:{}:\"{}\"",
             exp.code,
             exp.span.location_offset(),
-            restrict(DebugWidth::Long, exp.span)
+            restrict(w, exp.span)
         )?;
+        if let Some(related) = exp.related {
+            write!(f, " related to {}", related.location_offset())?;
+        }
         writeln!(f)?;
     }
 
@@ -273,6 +245,7 @@ fn debug_expect2_short<C: Code>(
 
 fn debug_suggest2_long<C: Code>(
     f: &mut impl fmt::Write,
+    w: DebugWidth,
     sug_vec: &Vec<&Suggest<'_, C>>,
     ind: usize,
 ) -> fmt::Result {
@@ -283,8 +256,11 @@ fn debug_suggest2_long<C: Code>(
             "{}:{}:\"{}\"",
             sug.code,
             sug.span.location_offset(),
-            restrict(DebugWidth::Long, sug.span)
+            restrict(w, sug.span)
         )?;
+        if let Some(related) = sug.related {
+            write!(f, " related to {}", related.location_offset())?;
+        }
         writeln!(f)?;
     }
 
@@ -1,5 +1,5 @@
 use crate::error::DebugWidth;
-use crate::Span;
+use crate::{ByteSpan, Span};
 use nom::bytes::complete::take_while_m_n;
 use nom::InputIter;
 
@@ -12,6 +12,7 @@ pub fn restrict(w: DebugWidth, span: Span<'_>) -> String {
         DebugWidth::Short => restrict_n(20, span),
         DebugWidth::Medium => restrict_n(40, span),
         DebugWidth::Long => restrict_n(60, span),
+        DebugWidth::Custom(n) => restrict_n(n, span),
     }
 }
 
@@ -22,12 +23,109 @@ pub fn restrict_n(max_len: usize, span: Span<'_>) -> String {
             Err(_) => "?error?",
         };
 
-    if span.len() > max_len {
+    // max_len counts chars, compare the bytes.
+    if shortened.len() < span.len() {
         shortened
             .escape_default()
-            .chain("...".iter_elements())
+            .chain("…".iter_elements())
             .collect()
     } else {
         shortened.escape_default().collect()
     }
 }
+
+/// Same as restrict_n but doesn't escape the text. Only control chars are
+/// replaced with a placeholder. Use this for messages aimed at the user.
+pub fn restrict_raw_n(max_len: usize, span: Span<'_>) -> String {
+    let shortened =
+        match take_while_m_n::<_, _, nom::error::Error<Span<'_>>>(0, max_len, |_c| true)(span) {
+            Ok((_rest, short)) => *short,
+            Err(_) => "?error?",
+        };
+
+    let raw = shortened
+        .chars()
+        .map(|c| if c.is_control() { '\u{FFFD}' } else { c });
+
+    if shortened.len() < span.len() {
+        raw.chain("…".iter_elements()).collect()
+    } else {
+        raw.collect()
+    }
+}
+
+/// Same as restrict_n for a byte input. Non-printable bytes are escaped.
+pub fn restrict_bytes_n(max_len: usize, span: ByteSpan<'_>) -> String {
+    let fragment = *span.fragment();
+    let shortened = &fragment[..max_len.min(fragment.len())];
+
+    let mut res: String = shortened
+        .iter()
+        .flat_map(|v| std::ascii::escape_default(*v))
+        .map(char::from)
+        .collect();
+    if shortened.len() < fragment.len() {
+        res.push('…');
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::debug::{restrict, restrict_bytes_n, restrict_n, restrict_raw_n};
+    use crate::error::{DebugWidth, ParserError};
+    use crate::{ByteSpan, Code, Span};
+    use std::fmt::{Display, Formatter};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct DebugCode;
+
+    impl Code for DebugCode {
+        const NOM_ERROR: Self = DebugCode;
+        const NOM_FAILURE: Self = DebugCode;
+        const PARSE_INCOMPLETE: Self = DebugCode;
+    }
+
+    impl Display for DebugCode {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    #[test]
+    pub fn test_restrict() {
+        assert_eq!(restrict_n(20, Span::new("abc")), "abc");
+        assert_eq!(restrict_n(3, Span::new("abc")), "abc");
+        assert_eq!(restrict_n(2, Span::new("abc")), "ab…");
+        // 3 chars but 6 bytes
+        assert_eq!(restrict_n(3, Span::new("äöü")), "\\u{e4}\\u{f6}\\u{fc}");
+        assert_eq!(restrict_raw_n(3, Span::new("äöü")), "äöü");
+        assert_eq!(restrict_raw_n(2, Span::new("äöü")), "äö…");
+    }
+
+    #[test]
+    #[should_panic(expected = "must be above 2")]
+    pub fn test_custom_width() {
+        // would be the same as DebugWidth::Long
+        let _ = usize::from(DebugWidth::Custom(2));
+    }
+
+    #[test]
+    pub fn test_restrict_custom() {
+        let span = Span::new("abcdef");
+        assert_eq!(restrict(DebugWidth::Custom(3), span), "abc…");
+        assert_eq!(DebugWidth::from(Some(120)), DebugWidth::Custom(120));
+        assert_eq!(usize::from(DebugWidth::Custom(120)), 120);
+        assert_eq!(
+            format!("{:5?}", ParserError::<'_, DebugCode>::new(DebugCode, span)),
+            "ParserError DebugCode \"abcde…\"\n"
+        );
+    }
+
+    #[test]
+    pub fn test_restrict_bytes() {
+        assert_eq!(restrict_bytes_n(20, ByteSpan::new(b"abc")), "abc");
+        assert_eq!(restrict_bytes_n(2, ByteSpan::new(b"abc")), "ab…");
+        assert_eq!(restrict_bytes_n(3, ByteSpan::new(b"\x00a\n")), "\\x00a\\n");
+    }
+}
@@ -2,6 +2,13 @@ use crate::error::DebugWidth;
 use crate::rtracer::RTracer;
 use crate::Code;
 use std::fmt;
+use std::fmt::Debug;
+
+impl<'s, C: Code> Debug for RTracer<'s, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_rtracer(f, DebugWidth::Medium, self)
+    }
+}
 
 pub(crate) fn debug_rtracer<'s, C: Code>(
     o: &mut impl fmt::Write,
@@ -1,20 +1,57 @@
 use crate::debug::restrict;
 use crate::error::DebugWidth;
 use crate::tracer::{
-    CTracer, DebugTrack, EnterTrack, ErrTrack, ExitTrack, ExpectTrack, OkTrack, StepTrack,
-    SuggestTrack, Track,
+    CTracer, DebugTrack, EnterTrack, ErrTrack, ExitTrack, ExpectTrack, NoteTrack, OkTrack,
+    StepTrack, SuggestTrack, SyntheticTrack, TraceStyle, Track,
 };
 use crate::{Code, FilterFn};
 use std::fmt;
+use std::fmt::{Debug, Display};
+
+impl<'s, C: Code, const TRACK: bool> Debug for CTracer<'s, C, TRACK> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_tracer(f, DebugWidth::Medium, TraceStyle::default(), self, &|_| {
+            true
+        })
+    }
+}
 
 fn indent(f: &mut impl fmt::Write, ind: usize) -> fmt::Result {
     write!(f, "{}", " ".repeat(ind * 2))?;
     Ok(())
 }
 
+const ANSI_GREEN: &str = "\x1b[32m";
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+fn debug_track_styled<C: Code>(
+    o: &mut impl fmt::Write,
+    w: DebugWidth,
+    style: TraceStyle,
+    ind: usize,
+    t: &Track<'_, C>,
+) -> fmt::Result {
+    write!(o, "{}", " ".repeat(ind * style.indent))?;
+    let color = match t {
+        Track::Enter(_) if style.color => Some(ANSI_GREEN),
+        Track::Err(_) if style.color => Some(ANSI_RED),
+        _ => None,
+    };
+    if let Some(color) = color {
+        write!(o, "{}", color)?;
+        debug_track(o, w, t)?;
+        write!(o, "{}", ANSI_RESET)?;
+    } else {
+        debug_track(o, w, t)?;
+    }
+    writeln!(o)
+}
+
 pub(crate) fn debug_tracer<'s, C: Code, const TRACK: bool>(
     o: &mut impl fmt::Write,
     w: DebugWidth,
+    style: TraceStyle,
     trace: &CTracer<'s, C, TRACK>,
     filter: FilterFn<'_, C>,
 ) -> fmt::Result {
@@ -27,21 +64,19 @@ pub(crate) fn debug_tracer<'s, C: Code, const TRACK: bool>(
             Track::Enter(_) => {
                 if filter(t) {
                     ind += 1;
-                    indent(o, ind)?;
-                    debug_track(o, w, t)?;
-                    writeln!(o)?;
+                    debug_track_styled(o, w, style, ind, t)?;
                 }
             }
             Track::Step(_)
             | Track::Debug(_)
+            | Track::Note(_)
+            | Track::Synthetic(_)
             | Track::Expect(_)
             | Track::Suggest(_)
             | Track::Ok(_)
             | Track::Err(_) => {
                 if filter(t) {
-                    indent(o, ind)?;
-                    debug_track(o, w, t)?;
-                    writeln!(o)?;
+                    debug_track_styled(o, w, style, ind, t)?;
                 }
             }
             Track::Exit(_) => {
@@ -82,11 +117,136 @@ pub(crate) fn debug_tracer<'s, C: Code, const TRACK: bool>(
     Ok(())
 }
 
+/// One line of the trace, same as debug_tracer.
+pub(crate) fn debug_track_line<C: Code>(
+    o: &mut impl fmt::Write,
+    w: DebugWidth,
+    ind: usize,
+    t: &Track<'_, C>,
+) -> fmt::Result {
+    indent(o, ind)?;
+    debug_track(o, w, t)?;
+    writeln!(o)
+}
+
+/// The last n tracks, same format as debug_tracer.
+pub(crate) fn debug_tail<'s, C: Code, const TRACK: bool>(
+    o: &mut impl fmt::Write,
+    w: DebugWidth,
+    trace: &CTracer<'s, C, TRACK>,
+    n: usize,
+) -> fmt::Result {
+    let skip = trace.track.len().saturating_sub(n);
+
+    // indentation at the start of the tail.
+    let mut ind = 0;
+    for t in &trace.track[..skip] {
+        match t {
+            Track::Enter(_) => ind += 1,
+            Track::Exit(_) => ind -= 1,
+            _ => {}
+        }
+    }
+
+    writeln!(o, "trace ({} tracks skipped)", skip)?;
+    for t in &trace.track[skip..] {
+        match t {
+            Track::Enter(_) => {
+                ind += 1;
+                debug_track_line(o, w, ind, t)?;
+            }
+            Track::Exit(_) => {
+                ind -= 1;
+            }
+            _ => {
+                debug_track_line(o, w, ind, t)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Same as debug_tracer, but as nested markdown list.
+pub(crate) fn markdown_tracer<'s, C: Code, const TRACK: bool>(
+    o: &mut impl fmt::Write,
+    w: DebugWidth,
+    trace: &CTracer<'s, C, TRACK>,
+    filter: FilterFn<'_, C>,
+) -> fmt::Result {
+    let mut ind = 0;
+
+    for t in &*trace.track {
+        if !filter(t) {
+            continue;
+        }
+        match t {
+            Track::Enter(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- **{}** `{}`", v.func, restrict(w, v.span))?;
+                ind += 1;
+            }
+            Track::Exit(_) => {
+                ind -= 1;
+            }
+            Track::Step(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- step {} `{}`", v.step, restrict(w, v.span))?;
+            }
+            Track::Debug(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- debug {}", v.dbg)?;
+            }
+            Track::Note(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- note {}={}", v.key, v.value)?;
+            }
+            Track::Synthetic(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- synthetic {} `{}`", v.code, restrict(w, v.span))?;
+            }
+            Track::Expect(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- {} expect `{:?}`", v.usage, v.list)?;
+            }
+            Track::Suggest(v) => {
+                indent(o, ind)?;
+                writeln!(o, "- {} suggest `{:?}`", v.usage, v.list)?;
+            }
+            Track::Ok(v) => {
+                indent(o, ind)?;
+                if !v.span.is_empty() {
+                    writeln!(
+                        o,
+                        "- ok `{}` rest `{}`",
+                        restrict(w, v.span),
+                        restrict(w, v.rest)
+                    )?;
+                } else {
+                    writeln!(o, "- ok no match")?;
+                }
+            }
+            Track::Err(v) => {
+                indent(o, ind)?;
+                if v.backtracked {
+                    writeln!(o, "- err (backtracked {} bytes) {}", v.consumed, v.err)?;
+                } else {
+                    writeln!(o, "- err {}", v.err)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn debug_track<C: Code>(f: &mut impl fmt::Write, w: DebugWidth, v: &Track<'_, C>) -> fmt::Result {
     match v {
         Track::Enter(v) => debug_enter(f, w, v),
         Track::Step(v) => debug_step(f, w, v),
         Track::Debug(v) => debug_debug(f, w, v),
+        Track::Note(v) => debug_note(f, w, v),
+        Track::Synthetic(v) => debug_synthetic(f, w, v),
         Track::Expect(v) => debug_expect(f, w, v),
         Track::Suggest(v) => debug_suggest(f, w, v),
         Track::Ok(v) => debug_ok(f, w, v),
@@ -95,6 +255,35 @@ fn debug_track<C: Code>(f: &mut impl fmt::Write, w: DebugWidth, v: &Track<'_, C>
     }
 }
 
+/// Length of the truncated func name with DebugWidth::Short.
+const SHORT_NAME_LEN: usize = 12;
+
+/// Displays the func of a track. Uses Code::short_name() with
+/// DebugWidth::Short.
+struct FuncName<C>(DebugWidth, C);
+
+impl<C: Code> Display for FuncName<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            DebugWidth::Short => {
+                let short = self.1.short_name();
+                if !short.is_empty() {
+                    write!(f, "{}", short)
+                } else {
+                    let name = self.1.to_string();
+                    match name.char_indices().nth(SHORT_NAME_LEN) {
+                        Some((idx, _)) => write!(f, "{}…", &name[..idx]),
+                        None => write!(f, "{}", name),
+                    }
+                }
+            }
+            DebugWidth::Medium | DebugWidth::Long | DebugWidth::Custom(_) => {
+                write!(f, "{}", self.1)
+            }
+        }
+    }
+}
+
 fn debug_enter<C: Code>(
     f: &mut impl fmt::Write,
     w: DebugWidth,
@@ -102,12 +291,17 @@ fn debug_enter<C: Code>(
 ) -> fmt::Result {
     match w {
         DebugWidth::Short | DebugWidth::Medium => {
-            write!(f, "{}: enter with \"{}\"", v.func, restrict(w, v.span))
+            write!(
+                f,
+                "{}: enter with \"{}\"",
+                FuncName(w, v.func),
+                restrict(w, v.span)
+            )
         }
-        DebugWidth::Long => write!(
+        DebugWidth::Long | DebugWidth::Custom(_) => write!(
             f,
             "{}: enter with \"{}\" <<{:?}",
-            v.func,
+            FuncName(w, v.func),
             restrict(w, v.span),
             v.parents
         ),
@@ -121,13 +315,19 @@ fn debug_step<C: Code>(
 ) -> fmt::Result {
     match w {
         DebugWidth::Short | DebugWidth::Medium => {
-            write!(f, "{}: step {} \"{}\"", v.func, v.step, restrict(w, v.span))
+            write!(
+                f,
+                "{}: step {} \"{}\"",
+                FuncName(w, v.func),
+                v.step,
+                restrict(w, v.span)
+            )
         }
-        DebugWidth::Long => {
+        DebugWidth::Long | DebugWidth::Custom(_) => {
             write!(
                 f,
                 "{}: step {} \"{}\" <<{:?}",
-                v.func,
+                FuncName(w, v.func),
                 v.step,
                 restrict(w, v.span),
                 v.parents
@@ -142,8 +342,64 @@ fn debug_debug<C: Code>(
     v: &DebugTrack<'_, C>,
 ) -> fmt::Result {
     match w {
-        DebugWidth::Short | DebugWidth::Medium => write!(f, "{}: debug {}", v.func, v.dbg),
-        DebugWidth::Long => write!(f, "{}: debug {} <<{:?}", v.func, v.dbg, v.parents),
+        DebugWidth::Short | DebugWidth::Medium => {
+            write!(f, "{}: debug {}", FuncName(w, v.func), v.dbg)
+        }
+        DebugWidth::Long | DebugWidth::Custom(_) => {
+            write!(
+                f,
+                "{}: debug {} <<{:?}",
+                FuncName(w, v.func),
+                v.dbg,
+                v.parents
+            )
+        }
+    }
+}
+
+fn debug_note<C: Code>(
+    f: &mut impl fmt::Write,
+    w: DebugWidth,
+    v: &NoteTrack<'_, C>,
+) -> fmt::Result {
+    match w {
+        DebugWidth::Short | DebugWidth::Medium => {
+            write!(f, "{}: note {}={}", FuncName(w, v.func), v.key, v.value)
+        }
+        DebugWidth::Long | DebugWidth::Custom(_) => {
+            write!(
+                f,
+                "{}: note {}={} <<{:?}",
+                FuncName(w, v.func),
+                v.key,
+                v.value,
+                v.parents
+            )
+        }
+    }
+}
+
+fn debug_synthetic<C: Code>(
+    f: &mut impl fmt::Write,
+    w: DebugWidth,
+    v: &SyntheticTrack<'_, C>,
+) -> fmt::Result {
+    match w {
+        DebugWidth::Short | DebugWidth::Medium => write!(
+            f,
+            "{}: synthetic {} \"{}\"",
+            FuncName(w, v.func),
+            v.code,
+            restrict(w, v.span)
+        ),
+        DebugWidth::Long | DebugWidth::Custom(_) => write!(
+            f,
+            "{}: synthetic {} \"{}\" <<{:?}",
+            FuncName(w, v.func),
+            v.code,
+            restrict(w, v.span),
+            v.parents
+        ),
     }
 }
 
@@ -153,9 +409,29 @@ fn debug_expect<C: Code>(
     v: &ExpectTrack<'_, C>,
 ) -> fmt::Result {
     match w {
-        DebugWidth::Short => write!(f, "{}: {} expect {:?}", v.func, v.usage, v.list),
-        DebugWidth::Medium => write!(f, "{}: {} expect {:?}", v.func, v.usage, v.list),
-        DebugWidth::Long => write!(f, "{}: {} expect {:?}", v.func, v.usage, v.list),
+        DebugWidth::Short => write!(
+            f,
+            "{}: {} expect {:?}",
+            FuncName(w, v.func),
+            v.usage,
+            v.list
+        ),
+        DebugWidth::Medium => write!(
+            f,
+            "{}: {} expect {:?}",
+            FuncName(w, v.func),
+            v.usage,
+            v.list
+        ),
+        DebugWidth::Long | DebugWidth::Custom(_) => {
+            write!(
+                f,
+                "{}: {} expect {:?}",
+                FuncName(w, v.func),
+                v.usage,
+                v.list
+            )
+        }
     }
 }
 
@@ -165,25 +441,46 @@ fn debug_suggest<C: Code>(
     v: &SuggestTrack<'_, C>,
 ) -> fmt::Result {
     match w {
-        DebugWidth::Short => write!(f, "{}: {} suggest {:?}", v.func, v.usage, v.list),
-        DebugWidth::Medium => write!(f, "{}: {} suggest {:?}", v.func, v.usage, v.list),
-        DebugWidth::Long => write!(f, "{}: {} suggest {:?}", v.func, v.usage, v.list),
+        DebugWidth::Short => write!(
+            f,
+            "{}: {} suggest {:?}",
+            FuncName(w, v.func),
+            v.usage,
+            v.list
+        ),
+        DebugWidth::Medium => write!(
+            f,
+            "{}: {} suggest {:?}",
+            FuncName(w, v.func),
+            v.usage,
+            v.list
+        ),
+        DebugWidth::Long | DebugWidth::Custom(_) => {
+            write!(
+                f,
+                "{}: {} suggest {:?}",
+                FuncName(w, v.func),
+                v.usage,
+                v.list
+            )
+        }
     }
 }
 
 fn debug_ok<C: Code>(f: &mut impl fmt::Write, w: DebugWidth, v: &OkTrack<'_, C>) -> fmt::Result {
     match w {
-        DebugWidth::Short | DebugWidth::Medium | DebugWidth::Long => {
+        DebugWidth::Short | DebugWidth::Medium | DebugWidth::Long | DebugWidth::Custom(_) => {
             if !v.span.is_empty() {
                 write!(
                     f,
-                    "{}: ok -> [ {}, '{}' ]",
-                    v.func,
-                    restrict(w, v.span),
+                    "{}: ok matched {} bytes, rest at {} \"{}\"",
+                    FuncName(w, v.func),
+                    v.span.len(),
+                    v.rest.location_offset(),
                     restrict(w, v.rest)
                 )?;
             } else {
-                write!(f, "{}: ok -> no match", v.func)?;
+                write!(f, "{}: ok -> no match", FuncName(w, v.func))?;
             }
         }
     }
@@ -191,9 +488,13 @@ fn debug_ok<C: Code>(f: &mut impl fmt::Write, w: DebugWidth, v: &OkTrack<'_, C>)
 }
 
 fn debug_err<C: Code>(f: &mut impl fmt::Write, w: DebugWidth, v: &ErrTrack<'_, C>) -> fmt::Result {
+    write!(f, "{}: err ", FuncName(w, v.func))?;
+    if v.backtracked {
+        write!(f, "(backtracked {} bytes) ", v.consumed)?;
+    }
     match w {
-        DebugWidth::Short | DebugWidth::Medium => write!(f, "{}: err {} ", v.func, v.err),
-        DebugWidth::Long => write!(f, "{}: err {} <<{:?}", v.func, v.err, v.parents),
+        DebugWidth::Short | DebugWidth::Medium => write!(f, "{} ", v.err),
+        DebugWidth::Long | DebugWidth::Custom(_) => write!(f, "{} <<{:?}", v.err, v.parents),
     }
 }
 
@@ -203,8 +504,8 @@ fn debug_exit<C: Code>(
     v: &ExitTrack<'_, C>,
 ) -> fmt::Result {
     match w {
-        DebugWidth::Short | DebugWidth::Medium | DebugWidth::Long => {
-            write!(f, "{}: exit", v.func)
+        DebugWidth::Short | DebugWidth::Medium | DebugWidth::Long | DebugWidth::Custom(_) => {
+            write!(f, "{}: exit", FuncName(w, v.func))
         }
     }
 }
@@ -1,12 +1,17 @@
-use crate::debug::restrict;
+use crate::debug::{restrict, restrict_raw_n};
+use crate::span::span_range;
 use crate::tracer::CTracer;
 use crate::{Code, IntoParserError, IntoParserResultAddCode, ParserResult, Span};
 use nom::error::ErrorKind;
+use nom::IResult;
 use std::error::Error;
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
 /// Error for the Parser.
+#[derive(Clone)]
 pub struct ParserError<'s, C: Code> {
     /// Error code.
     pub code: C,
@@ -14,6 +19,11 @@ pub struct ParserError<'s, C: Code> {
     pub span: Span<'s>,
     /// Flag for Tracer.
     pub tracing: bool,
+    /// Created from a nom::Err::Failure. into_nom() keeps it a Failure.
+    pub failure: bool,
+    /// Parser call stack when the error was recorded by CTracer.
+    /// Empty for the other tracers.
+    pub stack: Vec<C>,
     /// Collected nom errors if any.
     pub hints: Vec<Hints<'s, C>>,
 }
@@ -25,6 +35,8 @@ impl<'s, C: Code> ParserError<'s, C> {
             code,
             span,
             tracing: false,
+            failure: false,
+            stack: Vec::new(),
             hints: Vec::new(),
         }
     }
@@ -35,10 +47,13 @@ impl<'s, C: Code> ParserError<'s, C> {
             code,
             span,
             tracing: false,
+            failure: false,
+            stack: Vec::new(),
             hints: vec![Hints::Suggest(Suggest {
                 code,
                 span,
-                // parents: vec![],
+                related: None,
+                seq: 0,
             })],
         }
     }
@@ -49,6 +64,8 @@ impl<'s, C: Code> ParserError<'s, C> {
             code,
             span,
             tracing: false,
+            failure: false,
+            stack: Vec::new(),
             hints: vec![Hints::Nom(Nom {
                 kind: nom_code,
                 span,
@@ -56,16 +73,179 @@ impl<'s, C: Code> ParserError<'s, C> {
         }
     }
 
+    /// New error with an empty span.
+    ///
+    /// The span isn't tied to any source, it's only a placeholder.
+    pub fn from_code(code: C) -> Self {
+        Self::new(code, Span::new(""))
+    }
+
+    /// New error with a synthetic zero-length span at offset and line.
+    /// Useful for errors found after parsing, when there is no Span at hand.
+    /// If only code, offset and line are needed use CodeError instead.
+    ///
+    /// # Safety
+    ///
+    /// The span isn't tied to any source, there is no text before the
+    /// fragment. Unless offset is 0, the caller must not use anything that
+    /// reads the text before the fragment:
+    /// * Span::get_column(), get_utf8_column() and get_line_beginning().
+    /// * ParserError::summary(), message(), to_jsonl() and lsp_diagnostic().
+    /// * source_up_to(), span_union() and the other functions of crate::span.
+    ///
+    /// location_offset(), location_line(), expect_sorted(), suggest_sorted()
+    /// and CodeError are fine.
+    pub unsafe fn at(code: C, offset: usize, line: u32) -> Self {
+        let span = Span::new_from_raw_offset(offset, line, "", ());
+        Self::new(code, span)
+    }
+
     /// Convert to a new error code.
     /// If the old one differs, it is added to the expect list.
+    /// Unless the same code at the same offset is already the last expect value.
     pub fn into_code(mut self, code: C) -> Self {
-        if self.code != code {
+        if self.code != code && !self.is_last_expect(self.code, self.span) {
             self.add_expect(self.code, self.span);
         }
         self.code = code;
         self
     }
 
+    /// Converts to another Code type, e.g. for the errors of an embedded
+    /// sub-grammar. The code, the call stack and the codes of all hints
+    /// are mapped with f.
+    pub fn map_code<C2: Code>(self, f: impl Fn(C) -> C2) -> ParserError<'s, C2> {
+        ParserError {
+            code: f(self.code),
+            span: self.span,
+            tracing: self.tracing,
+            failure: self.failure,
+            stack: self.stack.into_iter().map(&f).collect(),
+            hints: self
+                .hints
+                .into_iter()
+                .map(|v| match v {
+                    Hints::Nom(v) => Hints::Nom(v),
+                    Hints::Suggest(v) => Hints::Suggest(Suggest {
+                        code: f(v.code),
+                        span: v.span,
+                        related: v.related,
+                        seq: v.seq,
+                    }),
+                    Hints::Expect(v) => Hints::Expect(Expect {
+                        code: f(v.code),
+                        span: v.span,
+                        related: v.related,
+                        seq: v.seq,
+                    }),
+                    Hints::Trivia(v) => Hints::Trivia(v),
+                })
+                .collect(),
+        }
+    }
+
+    /// Marks the error as not recoverable, like nom::Err::Failure.
+    pub fn into_failure(mut self) -> Self {
+        self.failure = true;
+        self
+    }
+
+    /// Error is not recoverable. See into_failure().
+    pub fn is_failure(&self) -> bool {
+        self.failure
+    }
+
+    /// Error code.
+    ///
+    /// Prefer the accessors over the public fields, the fields may become
+    /// private in the future.
+    pub fn code(&self) -> C {
+        self.code
+    }
+
+    /// Error span.
+    pub fn span(&self) -> Span<'s> {
+        self.span
+    }
+
+    /// All collected hints.
+    pub fn hints(&self) -> &[Hints<'s, C>] {
+        &self.hints
+    }
+
+    /// Byte range of the error span.
+    pub fn byte_range(&self) -> Range<usize> {
+        span_range(self.span)
+    }
+
+    /// Code, offset and line of the error without any Span.
+    pub fn code_only(&self) -> (C, usize, u32) {
+        (
+            self.code,
+            self.span.location_offset(),
+            self.span.location_line(),
+        )
+    }
+
+    /// One line summary like "Code expected at line:column".
+    pub fn summary(&self) -> String {
+        format!(
+            "{} expected at {}:{}",
+            self.code,
+            self.span.location_line(),
+            self.span.get_utf8_column()
+        )
+    }
+
+    /// Message with the code and the expected codes, like "Code: expected A, B".
+    pub fn message(&self) -> String {
+        let expect = self.expect_sorted();
+        if expect.is_empty() {
+            self.code.to_string()
+        } else {
+            let codes: Vec<_> = expect
+                .iter()
+                .map(|v| match v.code.literal() {
+                    Some(lit) => format!("`{}`", lit),
+                    None => v.code.to_string(),
+                })
+                .collect();
+            format!("{}: expected {}", self.code, codes.join(", "))
+        }
+    }
+
+    /// One JSON object in a single line, for JSON Lines output.
+    /// Contains file, line, col, code and message.
+    pub fn to_jsonl(&self, file: &str) -> String {
+        format!(
+            "{{\"file\":\"{}\",\"line\":{},\"col\":{},\"code\":\"{}\",\"message\":\"{}\"}}",
+            json_escape(file),
+            self.span.location_line(),
+            self.span.get_utf8_column(),
+            json_escape(&self.code.to_string()),
+            json_escape(&self.message())
+        )
+    }
+
+    /// Span-free copy of the error.
+    pub fn to_code_error(&self) -> CodeError<C> {
+        CodeError::from(self)
+    }
+
+    // Is this code and offset the last expect value?
+    fn is_last_expect(&self, code: C, span: Span<'s>) -> bool {
+        let last = self.hints.iter().rev().find_map(|v| match v {
+            Hints::Expect(e) => Some(e),
+            _ => None,
+        });
+        match last {
+            Some(last) => {
+                last.code == code && last.span.location_offset() == span.location_offset()
+            }
+            None => false,
+        }
+    }
+
     /// Special error code. Encodes errors occurring at the margins.
     pub fn is_special(&self) -> bool {
         self.code.is_special()
@@ -76,6 +256,21 @@ impl<'s, C: Code> ParserError<'s, C> {
         !self.code.is_special()
     }
 
+    /// Is this the PARSE_INCOMPLETE code?
+    pub fn is_incomplete(&self) -> bool {
+        self.code == C::PARSE_INCOMPLETE
+    }
+
+    /// Is this the NOM_ERROR code?
+    pub fn is_nom_error(&self) -> bool {
+        self.code == C::NOM_ERROR
+    }
+
+    /// Is this the NOM_FAILURE code?
+    pub fn is_nom_failure(&self) -> bool {
+        self.code == C::NOM_FAILURE
+    }
+
     /// Is this one of the nom errorkind codes?
     pub fn is_kind(&self, kind: ErrorKind) -> bool {
         for n in &self.hints {
@@ -102,49 +297,30 @@ impl<'s, C: Code> ParserError<'s, C> {
 
     /// Find two consecutive expect values.
     pub fn is_expected2(&self, code: C, parent: C) -> bool {
-        let mut it = self
+        let expect: Vec<C> = self
             .hints
             .iter()
             .filter_map(|v| match v {
                 Hints::Nom(_) => None,
                 Hints::Suggest(_) => None,
-                Hints::Expect(e) => Some(e),
+                Hints::Trivia(_) => None,
+                Hints::Expect(e) => Some(e.code),
             })
-            .rev()
-            .peekable();
+            .collect();
 
         // if this code is ever replaced it will be pushed after the
         // code on the vec. so this will be the parent and the last
         // code on the vec will be the code in question.
+        // the parent itself may have been added to the vec after that.
         if self.code == parent {
-            if let Some(nexp) = it.peek() {
-                if nexp.code == code {
+            if let Some(last) = expect.iter().rev().find(|v| **v != parent) {
+                if *last == code {
                     return true;
                 }
             }
         }
 
-        let mut it = self
-            .hints
-            .iter()
-            .filter_map(|v| match v {
-                Hints::Nom(_) => None,
-                Hints::Suggest(_) => None,
-                Hints::Expect(e) => Some(e),
-            })
-            .rev()
-            .peekable();
-
-        while let Some(exp) = it.next() {
-            if exp.code == parent {
-                if let Some(nexp) = it.peek() {
-                    if nexp.code == code {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
+        expect.windows(2).any(|v| v[0] == code && v[1] == parent)
     }
 
     /// ParseIncomplete variant.
@@ -152,6 +328,12 @@ impl<'s, C: Code> ParserError<'s, C> {
         ParserError::new(C::PARSE_INCOMPLETE, span)
     }
 
+    /// ParseIncomplete for a nom::Err::Incomplete.
+    /// nom doesn't tell where this happened, so the span is empty.
+    pub fn nom_incomplete() -> ParserError<'s, C> {
+        ParserError::new(C::PARSE_INCOMPLETE, Span::new(""))
+    }
+
     /// Return any nom error codes.
     pub fn nom(&self) -> Vec<&Nom<'s>> {
         self.hints
@@ -163,9 +345,33 @@ impl<'s, C: Code> ParserError<'s, C> {
             .collect()
     }
 
+    /// Span of the first nom error. This is where nom really failed,
+    /// regardless of later into_code() or append() calls.
+    pub fn nom_span(&self) -> Option<Span<'s>> {
+        self.hints.iter().find_map(|v| match v {
+            Hints::Nom(n) => Some(n.span),
+            _ => None,
+        })
+    }
+
     /// Adds some expect values.
     pub fn add_expect(&mut self, code: C, span: Span<'s>) {
-        self.hints.push(Hints::Expect(Expect { code, span }))
+        self.hints.push(Hints::Expect(Expect {
+            code,
+            span,
+            related: None,
+            seq: 0,
+        }))
+    }
+
+    /// Adds an expect value with a secondary span.
+    pub fn add_expect_related(&mut self, code: C, span: Span<'s>, related: Span<'s>) {
+        self.hints.push(Hints::Expect(Expect {
+            code,
+            span,
+            related: Some(related),
+            seq: 0,
+        }))
     }
 
     /// Adds some expect values.
@@ -175,9 +381,49 @@ impl<'s, C: Code> ParserError<'s, C> {
         }
     }
 
+    /// Adds an expect value for each code and span.
+    pub fn add_expect_all(&mut self, items: &[(C, Span<'s>)]) {
+        for (code, span) in items {
+            self.add_expect(*code, *span);
+        }
+    }
+
     /// Adds some suggest value.
     pub fn add_suggest(&mut self, code: C, span: Span<'s>) {
-        self.hints.push(Hints::Suggest(Suggest { code, span }))
+        self.hints.push(Hints::Suggest(Suggest {
+            code,
+            span,
+            related: None,
+            seq: 0,
+        }))
+    }
+
+    /// Adds a suggest value with a secondary span.
+    pub fn add_suggest_related(&mut self, code: C, span: Span<'s>, related: Span<'s>) {
+        self.hints.push(Hints::Suggest(Suggest {
+            code,
+            span,
+            related: Some(related),
+            seq: 0,
+        }))
+    }
+
+    /// Adds some skipped trivia.
+    pub fn append_trivia(&mut self, trivia: Vec<Trivia<'s>>) {
+        for t in trivia.into_iter() {
+            self.hints.push(Hints::Trivia(t));
+        }
+    }
+
+    /// Returns the trivia hints.
+    pub fn trivia(&self) -> Vec<&Trivia<'s>> {
+        self.hints
+            .iter()
+            .filter_map(|v| match v {
+                Hints::Trivia(t) => Some(t),
+                _ => None,
+            })
+            .collect()
     }
 
     /// Adds some suggest values.
@@ -187,6 +433,13 @@ impl<'s, C: Code> ParserError<'s, C> {
         }
     }
 
+    /// Adds a suggest value for each code and span.
+    pub fn add_suggest_all(&mut self, items: &[(C, Span<'s>)]) {
+        for (code, span) in items {
+            self.add_suggest(*code, *span);
+        }
+    }
+
     /// Extracts all the collected expect and suggest values.
     pub fn to_results(&mut self) -> (Vec<Expect<'s, C>>, Vec<Suggest<'s, C>>) {
         (self.to_expect(), self.to_suggest())
@@ -203,6 +456,23 @@ impl<'s, C: Code> ParserError<'s, C> {
             .collect()
     }
 
+    /// Returns the collected expect values where the code matches the predicate.
+    /// E.g. to hide internal codes from the user.
+    pub fn expects_where(&self, f: impl Fn(C) -> bool) -> Vec<&Expect<'s, C>> {
+        self.hints
+            .iter()
+            .filter_map(|v| match v {
+                Hints::Expect(n) if f(n.code) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Clones the collected expect values.
+    pub fn cloned_expect(&self) -> Vec<Expect<'s, C>> {
+        self.expect_as_ref().into_iter().cloned().collect()
+    }
+
     /// Extracts the collected expect values.
     pub fn to_expect(&mut self) -> Vec<Expect<'s, C>> {
         let mut res = Vec::new();
@@ -226,6 +496,7 @@ impl<'s, C: Code> ParserError<'s, C> {
                 Hints::Expect(v) => res.push(v),
                 Hints::Nom(_) => unreachable!(),
                 Hints::Suggest(_) => unreachable!(),
+                Hints::Trivia(_) => unreachable!(),
             }
         }
         res.reverse();
@@ -233,6 +504,13 @@ impl<'s, C: Code> ParserError<'s, C> {
         res
     }
 
+    /// Expect values sorted ascending by offset.
+    pub fn expect_sorted(&self) -> Vec<&Expect<'s, C>> {
+        let mut sorted = self.expect_as_ref();
+        sorted.sort_by_key(|v| v.span.location_offset());
+        sorted
+    }
+
     /// Get Expect grouped by offset into the string, starting with max first.
     pub fn expect_grouped_by_offset(&self) -> Vec<(usize, Vec<&Expect<'s, C>>)> {
         Expect::group_by_offset(self.expect_as_ref())
@@ -254,6 +532,34 @@ impl<'s, C: Code> ParserError<'s, C> {
             .collect()
     }
 
+    /// Returns the collected suggest values where the code matches the predicate.
+    pub fn suggests_where(&self, f: impl Fn(C) -> bool) -> Vec<&Suggest<'s, C>> {
+        self.hints
+            .iter()
+            .filter_map(|v| match v {
+                Hints::Suggest(n) if f(n.code) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Clones the collected suggest values.
+    pub fn cloned_suggest(&self) -> Vec<Suggest<'s, C>> {
+        self.suggest_as_ref().into_iter().cloned().collect()
+    }
+
+    /// Suggestions whose span touches the given offset.
+    /// The span may start at or contain the offset, or end right at it.
+    pub fn suggestions_at(&self, offset: usize) -> Vec<&Suggest<'s, C>> {
+        self.suggest_as_ref()
+            .into_iter()
+            .filter(|v| {
+                let range = span_range(v.span);
+                (range.start..=range.end).contains(&offset)
+            })
+            .collect()
+    }
+
     /// Extracts the collected expect values.
     pub fn to_suggest(&mut self) -> Vec<Suggest<'s, C>> {
         let mut res = Vec::new();
@@ -277,6 +583,7 @@ impl<'s, C: Code> ParserError<'s, C> {
                 Hints::Suggest(v) => res.push(v),
                 Hints::Nom(_) => unreachable!(),
                 Hints::Expect(_) => unreachable!(),
+                Hints::Trivia(_) => unreachable!(),
             }
         }
         res.reverse();
@@ -284,11 +591,36 @@ impl<'s, C: Code> ParserError<'s, C> {
         res
     }
 
+    /// Suggest values sorted ascending by offset.
+    pub fn suggest_sorted(&self) -> Vec<&Suggest<'s, C>> {
+        let mut sorted = self.suggest_as_ref();
+        sorted.sort_by_key(|v| v.span.location_offset());
+        sorted
+    }
+
     /// Get Suggest grouped by offset into the string, starting with max first.
     pub fn suggest_grouped_by_offset(&self) -> Vec<(usize, Vec<&Suggest<'s, C>>)> {
         Suggest::group_by_offset(self.suggest_as_ref())
     }
 
+    /// Suggestions as text edits, grouped by offset with max first.
+    /// The edits of one group are alternatives for a quick-fix.
+    /// Suggestions without a Code::literal() are skipped.
+    pub fn suggest_edits(&self) -> Vec<(usize, Vec<TextEdit>)> {
+        self.suggest_grouped_by_offset()
+            .into_iter()
+            .map(|(offset, grp)| {
+                (
+                    offset,
+                    grp.into_iter()
+                        .filter_map(|v| v.as_edit())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .filter(|(_, grp)| !grp.is_empty())
+            .collect()
+    }
+
     /// Get Suggest grouped by offset into the string, starting with max first.
     pub fn suggest_grouped_by_line(&self) -> Vec<(u32, Vec<&Suggest<'s, C>>)> {
         Suggest::group_by_line(self.suggest_as_ref())
@@ -322,8 +654,65 @@ impl<'s, C: Code> Display for ParserError<'s, C> {
     }
 }
 
+/// Compares code, span, the failure flag and the hint codes. The order of
+/// the hints and the internal tracing flag are ignored.
+///
+/// The stack is ignored too. Only CTracer records it, and the same error
+/// should compare equal regardless of the tracer used.
+impl<'s, C: Code> PartialEq for ParserError<'s, C> {
+    fn eq(&self, other: &Self) -> bool {
+        fn same_set<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+            a.iter().all(|v| b.contains(v)) && b.iter().all(|v| a.contains(v))
+        }
+
+        self.code == other.code
+            && self.failure == other.failure
+            && self.span.location_offset() == other.span.location_offset()
+            && self.span.fragment() == other.span.fragment()
+            && same_set(
+                &self.nom().iter().map(|v| v.kind).collect::<Vec<_>>(),
+                &other.nom().iter().map(|v| v.kind).collect::<Vec<_>>(),
+            )
+            && same_set(
+                &self
+                    .expect_as_ref()
+                    .iter()
+                    .map(|v| v.code)
+                    .collect::<Vec<_>>(),
+                &other
+                    .expect_as_ref()
+                    .iter()
+                    .map(|v| v.code)
+                    .collect::<Vec<_>>(),
+            )
+            && same_set(
+                &self
+                    .suggest_as_ref()
+                    .iter()
+                    .map(|v| v.code)
+                    .collect::<Vec<_>>(),
+                &other
+                    .suggest_as_ref()
+                    .iter()
+                    .map(|v| v.code)
+                    .collect::<Vec<_>>(),
+            )
+    }
+}
+
 impl<'s, C: Code> Error for ParserError<'s, C> {}
 
+/// Consumes the error and yields all the hints.
+/// Read code and span before, they are not part of the iteration.
+impl<'s, C: Code> IntoIterator for ParserError<'s, C> {
+    type Item = Hints<'s, C>;
+    type IntoIter = std::vec::IntoIter<Hints<'s, C>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hints.into_iter()
+    }
+}
+
 /// Coop with nom.
 impl<'s, C: Code> nom::error::ParseError<Span<'s>> for ParserError<'s, C> {
     fn from_error_kind(span: Span<'s>, kind: ErrorKind) -> Self {
@@ -331,6 +720,8 @@ impl<'s, C: Code> nom::error::ParseError<Span<'s>> for ParserError<'s, C> {
             code: C::NOM_ERROR,
             span,
             tracing: false,
+            failure: false,
+            stack: Vec::new(),
             hints: vec![Hints::Nom(Nom { kind, span })],
         }
     }
@@ -341,6 +732,44 @@ impl<'s, C: Code> nom::error::ParseError<Span<'s>> for ParserError<'s, C> {
     }
 }
 
+/// Converts a ParserResult back to a nom IResult, to use a Parser inside
+/// of nom combinators. A failure becomes a nom::Err::Failure, everything
+/// else a nom::Err::Error.
+pub fn into_nom<'s, C: Code, O>(
+    result: ParserResult<'s, C, (Span<'s>, O)>,
+) -> IResult<Span<'s>, O, ParserError<'s, C>> {
+    match result {
+        Ok(v) => Ok(v),
+        Err(e) if e.failure => Err(nom::Err::Failure(e)),
+        Err(e) => Err(nom::Err::Error(e)),
+    }
+}
+
+// Escapes a string for a JSON string literal.
+fn json_escape(s: &str) -> String {
+    let mut buf = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+    buf
+}
+
+/// Sorts errors by the offset of the span and then by code.
+/// Code only guarantees PartialEq, so the key fn gives the order of the codes,
+/// eg `|c| c as u32` for a fieldless enum.
+/// The sort is stable, which gives a deterministic output for tests.
+pub fn sort_diagnostics<C: Code>(errors: &mut [ParserError<'_, C>], key: impl Fn(C) -> u32) {
+    errors.sort_by_key(|v| (v.span.location_offset(), key(v.code)));
+}
+
 impl<'s, C> From<nom::Err<ParserError<'s, C>>> for ParserError<'s, C>
 where
     C: Code,
@@ -348,8 +777,8 @@ where
     fn from(e: nom::Err<ParserError<'s, C>>) -> Self {
         match e {
             nom::Err::Error(e) => e,
-            nom::Err::Failure(e) => e,
-            nom::Err::Incomplete(_) => unreachable!(),
+            nom::Err::Failure(e) => e.into_failure(),
+            nom::Err::Incomplete(_) => ParserError::nom_incomplete(),
         }
     }
 }
@@ -373,8 +802,8 @@ where
     fn into_with_code(self, code: C) -> ParserError<'s, C> {
         match self {
             nom::Err::Error(e) => e.into_code(code),
-            nom::Err::Failure(e) => e.into_code(code),
-            nom::Err::Incomplete(_) => unreachable!(),
+            nom::Err::Failure(e) => e.into_failure().into_code(code),
+            nom::Err::Incomplete(_) => ParserError::nom_incomplete().into_code(code),
         }
     }
 }
@@ -398,8 +827,10 @@ where
     fn from(e: nom::Err<nom::error::Error<Span<'s>>>) -> Self {
         match e {
             nom::Err::Error(e) => ParserError::new_with_nom(C::NOM_ERROR, e.code, e.input),
-            nom::Err::Failure(e) => ParserError::new_with_nom(C::NOM_FAILURE, e.code, e.input),
-            nom::Err::Incomplete(_) => unreachable!(),
+            nom::Err::Failure(e) => {
+                ParserError::new_with_nom(C::NOM_FAILURE, e.code, e.input).into_failure()
+            }
+            nom::Err::Incomplete(_) => ParserError::nom_incomplete(),
         }
     }
 }
@@ -411,8 +842,8 @@ where
     fn into_with_code(self, code: C) -> ParserError<'s, C> {
         match self {
             nom::Err::Error(e) => ParserError::new_with_nom(code, e.code, e.input),
-            nom::Err::Failure(e) => ParserError::new_with_nom(code, e.code, e.input),
-            nom::Err::Incomplete(_) => unreachable!(),
+            nom::Err::Failure(e) => ParserError::new_with_nom(code, e.code, e.input).into_failure(),
+            nom::Err::Incomplete(_) => ParserError::nom_incomplete().into_code(code),
         }
     }
 }
@@ -430,7 +861,7 @@ where
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DebugWidth {
     /// Debug flag, can be set with width=0.
     Short,
@@ -438,12 +869,29 @@ pub enum DebugWidth {
     Medium,
     /// Debug flag, can be set with width=2.
     Long,
+    /// Same as Long, but with a custom text length.
+    /// Can be set with any width above 2.
+    ///
+    /// The length must be above 2 too, otherwise it can't be told apart
+    /// from Short, Medium and Long when used as format width.
+    /// Converting a smaller length to usize panics.
+    Custom(usize),
 }
 
+#[derive(Clone)]
 pub enum Hints<'s, C: Code> {
     Nom(Nom<'s>),
     Suggest(Suggest<'s, C>),
     Expect(Expect<'s, C>),
+    Trivia(Trivia<'s>),
+}
+
+/// Skipped whitespace or comments near the error.
+/// A low priority hint, eg to relate an error to a nearby comment.
+#[derive(Clone)]
+pub struct Trivia<'s> {
+    /// Span
+    pub span: Span<'s>,
 }
 
 /// Data gathered from nom.
@@ -462,27 +910,62 @@ pub struct Suggest<'s, C> {
     pub code: C,
     /// Span
     pub span: Span<'s>,
+    /// Secondary span, eg. the opening delimiter for a missing closing one.
+    pub related: Option<Span<'s>>,
+    /// Sequence number assigned by the tracer. 0 if added directly.
+    pub seq: u32,
+}
+
+/// Equal if code and offset are equal, the length of the span and
+/// the other fields are ignored. This is the key for deduplication.
+impl<'s, C: PartialEq> PartialEq for Suggest<'s, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.span.location_offset() == other.span.location_offset()
+    }
+}
+
+impl<'s, C: Eq> Eq for Suggest<'s, C> {}
+
+/// Hashes code and offset, same as PartialEq.
+impl<'s, C: Hash> Hash for Suggest<'s, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+        self.span.location_offset().hash(state);
+    }
 }
 
 impl<'s, C> Suggest<'s, C> {
     pub fn group_by_offset_owned<'a>(
         vec: &'a Vec<Suggest<'s, C>>,
-    ) -> Vec<(usize, Vec<&'a Suggest<'s, C>>)> {
+    ) -> Vec<(usize, Vec<&'a Suggest<'s, C>>)>
+    where
+        C: PartialEq,
+    {
         Self::group_by_offset(vec.iter().collect())
     }
 
     /// Get Suggest grouped by offset into the string, starting with max first.
+    /// Duplicate codes within a group are removed.
+    /// Within a group the latest comes first, ordered by the sequence number.
     pub fn group_by_offset<'a>(
         vec: Vec<&'a Suggest<'s, C>>,
-    ) -> Vec<(usize, Vec<&'a Suggest<'s, C>>)> {
+    ) -> Vec<(usize, Vec<&'a Suggest<'s, C>>)>
+    where
+        C: PartialEq,
+    {
         let mut sorted = vec;
         sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
+        sorted.sort_by(|a, b| {
+            b.span
+                .location_offset()
+                .cmp(&a.span.location_offset())
+                .then(b.seq.cmp(&a.seq))
+        });
 
         // per offset
         let mut grp_offset = 0;
         let mut grp = Vec::new();
-        let mut subgrp = Vec::new();
+        let mut subgrp: Vec<&Suggest<'s, C>> = Vec::new();
         for exp in &sorted {
             if exp.span.location_offset() != grp_offset {
                 if !subgrp.is_empty() {
@@ -492,7 +975,9 @@ impl<'s, C> Suggest<'s, C> {
                 grp_offset = exp.span.location_offset();
             }
 
-            subgrp.push(*exp);
+            if !subgrp.iter().any(|v| v.code == exp.code) {
+                subgrp.push(*exp);
+            }
         }
         if !subgrp.is_empty() {
             grp.push((grp_offset, subgrp));
@@ -511,7 +996,12 @@ impl<'s, C> Suggest<'s, C> {
     pub fn group_by_line<'a>(vec: Vec<&'a Suggest<'s, C>>) -> Vec<(u32, Vec<&'a Suggest<'s, C>>)> {
         let mut sorted = vec;
         sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
+        sorted.sort_by(|a, b| {
+            b.span
+                .location_offset()
+                .cmp(&a.span.location_offset())
+                .then(b.seq.cmp(&a.seq))
+        });
 
         // per offset
         let mut grp_line = 0;
@@ -536,6 +1026,18 @@ impl<'s, C> Suggest<'s, C> {
     }
 }
 
+impl<'s, C: Code> Suggest<'s, C> {
+    /// The suggestion as an insert of Code::literal() at the start of the span.
+    /// None if the code has no literal, there is no text to insert then.
+    pub fn as_edit(&self) -> Option<TextEdit> {
+        let offset = self.span.location_offset();
+        self.code.literal().map(|lit| TextEdit {
+            range: offset..offset,
+            new_text: lit.to_string(),
+        })
+    }
+}
+
 /// Expected tokens.
 #[derive(Clone)]
 pub struct Expect<'s, C> {
@@ -543,27 +1045,60 @@ pub struct Expect<'s, C> {
     pub code: C,
     /// Span.
     pub span: Span<'s>,
+    /// Secondary span, eg. the opening delimiter for a missing closing one.
+    pub related: Option<Span<'s>>,
+    /// Sequence number assigned by the tracer. 0 if added directly.
+    pub seq: u32,
+}
+
+/// Equal if code and offset are equal, the length of the span and
+/// the other fields are ignored. This is the key for deduplication.
+impl<'s, C: PartialEq> PartialEq for Expect<'s, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.span.location_offset() == other.span.location_offset()
+    }
+}
+
+impl<'s, C: Eq> Eq for Expect<'s, C> {}
+
+/// Hashes code and offset, same as PartialEq.
+impl<'s, C: Hash> Hash for Expect<'s, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+        self.span.location_offset().hash(state);
+    }
 }
 
 impl<'s, C> Expect<'s, C> {
     pub fn group_by_offset_owned<'a>(
         vec: &'a Vec<Expect<'s, C>>,
-    ) -> Vec<(usize, Vec<&'a Expect<'s, C>>)> {
+    ) -> Vec<(usize, Vec<&'a Expect<'s, C>>)>
+    where
+        C: PartialEq,
+    {
         Self::group_by_offset(vec.iter().collect())
     }
 
     /// Get Expect grouped by offset into the string, starting with max first.
-    pub fn group_by_offset<'a>(
-        vec: Vec<&'a Expect<'s, C>>,
-    ) -> Vec<(usize, Vec<&'a Expect<'s, C>>)> {
+    /// Duplicate codes within a group are removed.
+    /// Within a group the latest comes first, ordered by the sequence number.
+    pub fn group_by_offset<'a>(vec: Vec<&'a Expect<'s, C>>) -> Vec<(usize, Vec<&'a Expect<'s, C>>)>
+    where
+        C: PartialEq,
+    {
         let mut sorted = vec;
         sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
+        sorted.sort_by(|a, b| {
+            b.span
+                .location_offset()
+                .cmp(&a.span.location_offset())
+                .then(b.seq.cmp(&a.seq))
+        });
 
         // per offset
         let mut grp_offset = 0;
         let mut grp = Vec::new();
-        let mut subgrp = Vec::new();
+        let mut subgrp: Vec<&Expect<'s, C>> = Vec::new();
         for exp in &sorted {
             if exp.span.location_offset() != grp_offset {
                 if !subgrp.is_empty() {
@@ -573,7 +1108,9 @@ impl<'s, C> Expect<'s, C> {
                 grp_offset = exp.span.location_offset();
             }
 
-            subgrp.push(*exp);
+            if !subgrp.iter().any(|v| v.code == exp.code) {
+                subgrp.push(*exp);
+            }
         }
         if !subgrp.is_empty() {
             grp.push((grp_offset, subgrp));
@@ -592,7 +1129,12 @@ impl<'s, C> Expect<'s, C> {
     pub fn group_by_line<'a>(vec: Vec<&'a Expect<'s, C>>) -> Vec<(u32, Vec<&'a Expect<'s, C>>)> {
         let mut sorted = vec;
         sorted.reverse();
-        sorted.sort_by(|a, b| b.span.location_offset().cmp(&a.span.location_offset()));
+        sorted.sort_by(|a, b| {
+            b.span
+                .location_offset()
+                .cmp(&a.span.location_offset())
+                .then(b.seq.cmp(&a.seq))
+        });
 
         // per offset
         let mut grp_line = 0;
@@ -617,13 +1159,45 @@ impl<'s, C> Expect<'s, C> {
     }
 }
 
+/// User facing, the text is not escaped.
+impl<'s, C: Code> Display for Suggest<'s, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} \"{}\"", self.code, restrict_raw_n(20, self.span))
+    }
+}
+
+/// User facing, the text is not escaped.
+impl<'s, C: Code> Display for Expect<'s, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} \"{}\"", self.code, restrict_raw_n(20, self.span))
+    }
+}
+
 impl From<Option<usize>> for DebugWidth {
     fn from(value: Option<usize>) -> Self {
         match value {
             None | Some(0) => DebugWidth::Short,
             Some(1) => DebugWidth::Medium,
             Some(2) => DebugWidth::Long,
-            _ => DebugWidth::Short,
+            Some(n) => DebugWidth::Custom(n),
+        }
+    }
+}
+
+impl From<DebugWidth> for usize {
+    fn from(value: DebugWidth) -> Self {
+        match value {
+            DebugWidth::Short => 0,
+            DebugWidth::Medium => 1,
+            DebugWidth::Long => 2,
+            DebugWidth::Custom(n) => {
+                assert!(
+                    n > 2,
+                    "DebugWidth::Custom({}) is not supported, the length must be above 2",
+                    n
+                );
+                n
+            }
         }
     }
 }
@@ -650,3 +1224,50 @@ impl<'s, C: Code, const TRACK: bool> Display for TracerError<'s, C, TRACK> {
 }
 
 impl<'s, C: Code, const TRACK: bool> Error for TracerError<'s, C, TRACK> {}
+
+/// Replaces the text of the byte range with new_text.
+/// An empty range is an insert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Byte range.
+    pub range: Range<usize>,
+    /// Replacement.
+    pub new_text: String,
+}
+
+/// Error without references to the input.
+/// Use this where the Span is not available, e.g. at a library boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeError<C: Code> {
+    /// Error code.
+    pub code: C,
+    /// Byte offset.
+    pub offset: usize,
+    /// Line, starting with 1.
+    pub line: u32,
+}
+
+impl<'s, C: Code> From<&ParserError<'s, C>> for CodeError<C> {
+    fn from(value: &ParserError<'s, C>) -> Self {
+        let (code, offset, line) = value.code_only();
+        CodeError { code, offset, line }
+    }
+}
+
+impl<'s, C: Code> From<ParserError<'s, C>> for CodeError<C> {
+    fn from(value: ParserError<'s, C>) -> Self {
+        CodeError::from(&value)
+    }
+}
+
+impl<C: Code> Display for CodeError<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} offset {}",
+            self.code, self.line, self.offset
+        )
+    }
+}
+
+impl<C: Code> Error for CodeError<C> {}
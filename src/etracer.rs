@@ -0,0 +1,174 @@
+use crate::error::{Expect, Hints, ParserError, Suggest, Trivia};
+use crate::{Code, ParserResult, Span, Tracer};
+use std::mem;
+
+/// Error collection without tracing.
+///
+/// Maintains the expect and suggest stacks like CTracer, so the resulting
+/// ParserError carries the same hints. Nothing else is recorded.
+pub struct ErrorTracer<'s, C: Code> {
+    pub(crate) func: Vec<C>,
+    pub(crate) suggest: Vec<Vec<Suggest<'s, C>>>,
+    pub(crate) expect: Vec<Vec<Expect<'s, C>>>,
+    pub(crate) trivia: Vec<Vec<Trivia<'s>>>,
+}
+
+impl<'s, C: Code> Tracer<'s, C> for ErrorTracer<'s, C> {
+    /// New one.
+    fn new() -> Self {
+        Self {
+            func: Vec::new(),
+            suggest: Vec::new(),
+            expect: Vec::new(),
+            trivia: Vec::new(),
+        }
+    }
+
+    /// Enter a parser function. Absolutely necessary for the rest.
+    fn enter(&mut self, func: C, _span: Span<'s>) {
+        self.func.push(func);
+        self.suggest.push(Vec::new());
+        self.expect.push(Vec::new());
+        self.trivia.push(Vec::new());
+    }
+
+    /// Keep track of steps in a complicated parser.
+    fn step(&mut self, _step: &'static str, _span: Span<'s>) {}
+
+    /// Some detailed debug information.
+    fn debug<T: Into<String>>(&mut self, _step: T) {}
+
+    /// Notes are not recorded.
+    fn note(&mut self, _key: &'static str, _value: String) {}
+
+    /// Current parser function.
+    fn current(&self) -> Option<C> {
+        self.func.last().copied()
+    }
+
+    /// Adds a suggestion for the current stack frame.
+    fn suggest(&mut self, suggest: C, span: Span<'s>) {
+        self.add_suggest(suggest, span);
+    }
+
+    /// Adds an expectation for the current stack frame.
+    fn expect(&mut self, expect: C, span: Span<'s>) {
+        self.add_expect(expect, span);
+    }
+
+    /// Skipped trivia for the current stack frame.
+    fn trivia(&mut self, span: Span<'s>) {
+        self.trivia
+            .last_mut()
+            .expect("Vec<Trivia> is empty")
+            .push(Trivia { span });
+    }
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, _code: C, _span: Span<'s>) {}
+
+    /// Keep track of this error.
+    fn stash(&mut self, err: ParserError<'s, C>) {
+        self.add_expect(err.code, err.span);
+
+        let expect_vec = self.expect.last_mut().expect("Vec<Expect> is empty");
+        let suggest_vec = self.suggest.last_mut().expect("Vec<Suggest> is empty");
+
+        for hint in err.hints.into_iter() {
+            match hint {
+                Hints::Nom(_) => {}
+                Hints::Trivia(_) => {}
+                Hints::Suggest(v) => {
+                    suggest_vec.push(v);
+                }
+                Hints::Expect(v) => {
+                    expect_vec.push(v);
+                }
+            }
+        }
+    }
+
+    /// Write a track for an ok result.
+    fn ok<'t, T>(
+        &'t mut self,
+        rest: Span<'s>,
+        _span: Span<'s>,
+        val: T,
+    ) -> ParserResult<'s, C, (Span<'s>, T)> {
+        self.func.pop();
+        self.expect.pop().expect("Vec<Expect> is empty");
+        self.trivia.pop().expect("Vec<Trivia> is empty");
+
+        let mut suggest = self.suggest.pop().expect("Vec<Suggest> is empty");
+        // Keep suggests, same as CTracer.
+        if let Some(parent) = self.suggest.last_mut() {
+            parent.append(&mut suggest);
+        } else {
+            self.suggest.push(suggest);
+        }
+
+        Ok((rest, val))
+    }
+
+    /// Write a track for an error.
+    fn err<'t, T>(&'t mut self, mut err: ParserError<'s, C>) -> ParserResult<'s, C, T> {
+        err.tracing = true;
+
+        self.func.pop();
+        let exp = self.expect.pop().expect("Vec<Expect> is empty");
+        err.append_expect(exp);
+
+        let sug = self.suggest.pop().expect("Vec<Suggest> is empty");
+        err.append_suggest(sug);
+
+        let trivia = self.trivia.pop().expect("Vec<Trivia> is empty");
+        err.append_trivia(trivia);
+
+        Err(err)
+    }
+}
+
+// output
+impl<'s, C: Code> ErrorTracer<'s, C> {
+    pub fn to_results(&mut self) -> (Vec<Expect<'s, C>>, Vec<Suggest<'s, C>>) {
+        (self.to_expect(), self.to_suggest())
+    }
+
+    pub fn to_expect(&mut self) -> Vec<Expect<'s, C>> {
+        mem::take(&mut self.expect).into_iter().flatten().collect()
+    }
+
+    pub fn to_suggest(&mut self) -> Vec<Suggest<'s, C>> {
+        mem::take(&mut self.suggest).into_iter().flatten().collect()
+    }
+}
+
+// expect
+impl<'s, C: Code> ErrorTracer<'s, C> {
+    fn add_expect(&mut self, code: C, span: Span<'s>) {
+        self.expect
+            .last_mut()
+            .expect("Vec<Expect> is empty")
+            .push(Expect {
+                code,
+                span,
+                related: None,
+                seq: 0,
+            })
+    }
+}
+
+// suggest
+impl<'s, C: Code> ErrorTracer<'s, C> {
+    fn add_suggest(&mut self, code: C, span: Span<'s>) {
+        self.suggest
+            .last_mut()
+            .expect("Vec<Suggest> is empty")
+            .push(Suggest {
+                code,
+                span,
+                related: None,
+                seq: 0,
+            })
+    }
+}
@@ -0,0 +1,270 @@
+//!
+//! Owned input.
+//!
+
+use crate::error::{CodeError, ParserError};
+use crate::{Code, ParserResult, Span};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+use std::ops::Deref;
+use std::path::Path;
+use std::{fmt, fs, io};
+
+/// Holds the text for a parser that is only known at runtime.
+///
+/// Keep the ParseInput alive as long as the parse result and hand out
+/// spans with span(). It derefs to str, so it can be used everywhere
+/// a &str is expected, e.g. with test_parse(&input, ...).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseInput {
+    txt: String,
+}
+
+impl ParseInput {
+    /// New one.
+    pub fn new(txt: impl Into<String>) -> Self {
+        Self { txt: txt.into() }
+    }
+
+    /// Reads the input from a file.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            txt: fs::read_to_string(path)?,
+        })
+    }
+
+    /// A span for the complete input.
+    pub fn span(&self) -> Span<'_> {
+        Span::new(&self.txt)
+    }
+
+    /// The complete input.
+    pub fn as_str(&self) -> &str {
+        &self.txt
+    }
+
+    /// Returns the text.
+    pub fn into_string(self) -> String {
+        self.txt
+    }
+}
+
+impl Deref for ParseInput {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txt
+    }
+}
+
+impl AsRef<str> for ParseInput {
+    fn as_ref(&self) -> &str {
+        &self.txt
+    }
+}
+
+impl From<String> for ParseInput {
+    fn from(txt: String) -> Self {
+        Self { txt }
+    }
+}
+
+impl From<&str> for ParseInput {
+    fn from(txt: &str) -> Self {
+        Self {
+            txt: txt.to_string(),
+        }
+    }
+}
+
+/// Feeds a BufRead to the parser in chunks of whole lines.
+///
+/// The spans are relative to the buffer, they are not re-based to the
+/// position in the stream. A LocatedSpan can't carry an offset beyond
+/// its fragment without pointing to memory that doesn't exist.
+/// Instead offset() and line() give the position of the buffer in the
+/// stream, and code_error() converts an error to the position in the stream.
+#[derive(Debug)]
+pub struct ChunkedInput<R> {
+    reader: R,
+    buf: String,
+    offset: usize,
+    line: u32,
+    eof: bool,
+}
+
+impl<R: BufRead> ChunkedInput<R> {
+    /// New one.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            offset: 0,
+            line: 1,
+            eof: false,
+        }
+    }
+
+    /// Appends the next line to the buffer. Returns false at the end of the input.
+    pub fn fill(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+        if self.reader.read_line(&mut self.buf)? == 0 {
+            self.eof = true;
+        }
+        Ok(!self.eof)
+    }
+
+    /// The buffered input.
+    pub fn span(&self) -> Span<'_> {
+        Span::new(self.buf.as_str())
+    }
+
+    /// Byte offset of the buffer in the stream.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Line of the buffer start in the stream, starting with 1.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Drops the buffered input up to the given offset into the buffer.
+    /// Use the location_offset() of the rest.
+    ///
+    /// Fails if the offset is beyond the buffer or not at a char boundary.
+    pub fn consume(&mut self, offset: usize) -> io::Result<()> {
+        if !self.buf.is_char_boundary(offset) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "invalid offset {} for a buffer of {}",
+                    offset,
+                    self.buf.len()
+                ),
+            ));
+        }
+        let lines = memchr::memchr_iter(b'\n', &self.buf.as_bytes()[..offset]).count();
+        self.buf.drain(..offset);
+        self.offset += offset;
+        self.line += lines as u32;
+        Ok(())
+    }
+
+    /// Converts an error for the buffer to the position in the stream.
+    pub fn code_error<C: Code>(&self, err: &ParserError<'_, C>) -> CodeError<C> {
+        let (code, offset, line) = err.code_only();
+        CodeError {
+            code,
+            offset: self.offset + offset,
+            line: self.line + line - 1,
+        }
+    }
+
+    /// The buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// The end of the input has been reached.
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+}
+
+/// Error of parse_reader.
+#[derive(Debug)]
+pub enum ChunkError<C: Code> {
+    /// Reading failed.
+    Io(io::Error),
+    /// Parsing failed.
+    Parse(CodeError<C>),
+}
+
+impl<C: Code> Display for ChunkError<C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkError::Io(e) => write!(f, "{}", e),
+            ChunkError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<C: Code> Error for ChunkError<C> {}
+
+impl<C: Code> From<io::Error> for ChunkError<C> {
+    fn from(e: io::Error) -> Self {
+        ChunkError::Io(e)
+    }
+}
+
+enum Step<C: Code> {
+    Token(usize),
+    More,
+    Failed(CodeError<C>),
+}
+
+/// Parses the input from a reader. The parser is called repeatedly for
+/// the buffered input and each value is handed to the sink. Parsing stops
+/// when all of the input has been consumed.
+///
+/// The input is read line by line. A token that is not complete at the
+/// end of the buffer must be reported with PARSE_INCOMPLETE, then the
+/// next line is read and the parser is called again. Streaming nom parsers
+/// do this when the nom::Err is converted with from()/?, tokens that
+/// never contain a line break can use the complete parsers.
+///
+/// The second argument of the parser is the eof flag. Once it is set no more
+/// input will follow, and the parser should switch to the complete parsers
+/// to finish the last token. Trailing whitespace can be consumed without
+/// a value by using an Option as output.
+///
+/// The spans given to the parser are relative to the buffer, not re-based
+/// to the stream. The sink gets the offset of the buffer in the stream
+/// together with the value. The errors have the position in the stream.
+///
+/// The values must not borrow from the input, as the buffer is dropped
+/// after each token.
+pub fn parse_reader<R, C, O>(
+    reader: R,
+    mut parser: impl for<'a> FnMut(Span<'a>, bool) -> ParserResult<'a, C, (Span<'a>, O)>,
+    mut sink: impl FnMut(usize, O),
+) -> Result<(), ChunkError<C>>
+where
+    R: BufRead,
+    C: Code,
+{
+    let mut input = ChunkedInput::new(reader);
+
+    loop {
+        if input.is_empty() && !input.fill()? {
+            return Ok(());
+        }
+
+        let span = input.span();
+        let step = match parser(span, input.is_eof()) {
+            Ok((rest, _)) if rest.location_offset() == 0 => Step::Failed(CodeError {
+                code: C::NOM_ERROR,
+                offset: input.offset(),
+                line: input.line(),
+            }),
+            Ok((rest, val)) => {
+                sink(input.offset(), val);
+                Step::Token(rest.location_offset())
+            }
+            Err(e) if e.is_incomplete() && !input.is_eof() => Step::More,
+            Err(e) => Step::Failed(input.code_error(&e)),
+        };
+
+        match step {
+            Step::Token(offset) => input.consume(offset)?,
+            Step::More => {
+                input.fill()?;
+            }
+            Step::Failed(e) => return Err(ChunkError::Parse(e)),
+        }
+    }
+}
@@ -1,24 +1,41 @@
 #![doc=include_str!("../readme.md")]
 
+pub mod combinators;
 mod debug;
 pub mod error;
+pub mod etracer;
+pub mod input;
+#[cfg(feature = "lsp-types")]
+pub mod lsp;
 pub mod notracer;
 pub mod rtracer;
 pub mod span;
+pub mod stracer;
+pub mod synctracer;
 pub mod test;
 pub mod test2;
 pub mod tracer;
+#[cfg(feature = "tracing")]
+pub mod ttracer;
 
-pub use crate::debug::restrict_n;
+pub use crate::debug::{restrict_bytes_n, restrict_n, restrict_raw_n};
 
 use crate::error::ParserError;
 use crate::tracer::Track;
 use nom_locate::LocatedSpan;
 use std::fmt::{Debug, Display};
+use std::hash::Hash;
 
 /// Standard input type.
 pub type Span<'s> = LocatedSpan<&'s str>;
 
+/// Input type for binary data. Binary input can't be parsed yet.
+///
+/// Only restrict_bytes_n supports this for now. ParserError, the Tracer
+/// trait and all tracers are still fixed to Span, so a parser can't
+/// return errors or traces for a ByteSpan.
+pub type ByteSpan<'s> = LocatedSpan<&'s [u8]>;
+
 /// Result type.
 pub type ParserResult<'s, C, O> = Result<O, ParserError<'s, C>>;
 
@@ -37,8 +54,33 @@ pub trait Code: Copy + Display + Debug + PartialEq {
     fn is_special(&self) -> bool {
         *self == Self::NOM_ERROR || *self == Self::NOM_FAILURE || *self == Self::PARSE_INCOMPLETE
     }
+
+    /// Short name for the trace with DebugWidth::Short.
+    ///
+    /// The default returns "", then the trace uses the Display output
+    /// truncated to a few characters.
+    fn short_name(&self) -> &'static str {
+        ""
+    }
+
+    /// The literal text for codes of terminals, e.g. ")" or "**".
+    ///
+    /// Used for the text of Suggest::as_edit() and in the error message.
+    /// Other codes return None. The error message uses the Display of the
+    /// code then, and Suggest::as_edit() returns no edit.
+    fn literal(&self) -> Option<&'static str> {
+        None
+    }
 }
 
+/// Code that can be used as a HashMap key.
+///
+/// Implemented for every Code that is also Eq + Hash. Derive Hash on the
+/// code enum of your grammar to get the HashMap based aggregations.
+pub trait CodeHash: Code + Eq + Hash {}
+
+impl<C> CodeHash for C where C: Code + Eq + Hash {}
+
 /// Adds a span as location and converts the foreign error to a ParserError.
 pub trait IntoParserResultAddSpan<'s, C, O>
 where
@@ -82,6 +124,40 @@ pub trait Parser<'s, O, C: Code> {
         trace: &'t mut impl Tracer<'s, C>,
         rest: Span<'s>,
     ) -> ParserResult<'s, C, (Span<'s>, O)>;
+
+    /// Context for the errors of child parsers.
+    ///
+    /// If this returns a code, parse_child reports a failing child with
+    /// track_as and this code. Otherwise it just tracks the error.
+    fn context() -> Option<C> {
+        None
+    }
+
+    /// Runs a child parser and tracks a failure. Uses context() if set.
+    fn parse_child<'t, O2, P: Parser<'s, O2, C>>(
+        trace: &'t mut impl Tracer<'s, C>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, C, (Span<'s>, O2)> {
+        let result = P::parse(trace, rest);
+        match Self::context() {
+            Some(code) => result.track_as(trace, code),
+            None => result.track(trace),
+        }
+    }
+
+    /// Parses the expression and requires that all of the input is consumed.
+    /// Fails with PARSE_INCOMPLETE at the leftover rest otherwise.
+    fn parse_complete<'t>(
+        trace: &'t mut impl Tracer<'s, C>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, C, O> {
+        let (rest, val) = Self::parse(trace, rest)?;
+        if rest.is_empty() {
+            Ok(val)
+        } else {
+            Err(ParserError::parse_incomplete(rest))
+        }
+    }
 }
 
 /// Trait for one parser with configuration.
@@ -102,20 +178,49 @@ pub trait ConfParser<'s, O, C: Code> {
     ) -> ParserResult<'s, C, (Span<'s>, O)>;
 }
 
+/// Trait for one parser with mutable user state.
+///
+/// The state is passed through all the parser calls, eg for a symbol table.
+/// The tracer stays separate.
+pub trait StatefulParser<'s, S, O, C: Code> {
+    /// Function and error code.
+    fn id() -> C;
+
+    /// Possible look-ahead.
+    fn lah(_state: &S, span: Span<'s>) -> bool {
+        !span.is_empty()
+    }
+
+    /// Parses the expression.
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, C>,
+        state: &mut S,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, C, (Span<'s>, O)>;
+}
+
 /// Treats the result of a parser as optional.
 ///
 /// The exact return value is defined in the impl, but should include some Option<..>.
 pub trait ParseAsOptional<'s, C: Code, O> {
+    /// Type of the parsed token.
+    type Token;
+
     /// Returns a ParserResult.
     fn optional(self) -> ParserResult<'s, C, O>;
     /// Returns a ParserResult.
     /// The original ParserError can be processed with the closure.
     fn optional_with(self, err_op: &dyn Fn(ParserError<'s, C>)) -> ParserResult<'s, C, O>;
+    /// Returns a ParserResult.
+    /// Uses the default value instead of None.
+    fn optional_or(self, default: Self::Token) -> ParserResult<'s, C, (Span<'s>, Self::Token)>;
 }
 
 impl<'s, C: Code, O> ParseAsOptional<'s, C, (Span<'s>, Option<O>)>
     for ParserResult<'s, C, (Span<'s>, O)>
 {
+    type Token = O;
+
     /// Returns None for any Err
     fn optional(self) -> ParserResult<'s, C, (Span<'s>, Option<O>)> {
         match self {
@@ -138,24 +243,34 @@ impl<'s, C: Code, O> ParseAsOptional<'s, C, (Span<'s>, Option<O>)>
             }
         }
     }
+
+    /// Returns the default for any Err.
+    fn optional_or(self, default: O) -> ParserResult<'s, C, (Span<'s>, O)> {
+        match self {
+            Ok((rest, tok)) => Ok((rest, tok)),
+            Err(e) => Ok((e.span, default)),
+        }
+    }
 }
 
 impl<'s, C: Code> ParseAsOptional<'s, C, (Span<'s>, Option<Span<'s>>)> for ParserNomResult<'s, C> {
+    type Token = Span<'s>;
+
     /// Returns nom::Err::Error as None.
     /// Returns nom::Err::Failure as Err.
-    /// Panics for nom::Err::Incomplete.
+    /// Returns nom::Err::Incomplete as Err with PARSE_INCOMPLETE.
     fn optional(self) -> ParserResult<'s, C, (Span<'s>, Option<Span<'s>>)> {
         match self {
             Ok((rest, tok)) => Ok((rest, Some(tok))),
             Err(nom::Err::Error(e)) => Ok((e.span, None)),
-            Err(nom::Err::Failure(e)) => Err(e.into()),
-            Err(nom::Err::Incomplete(_)) => unreachable!(),
+            Err(nom::Err::Failure(e)) => Err(e.into_failure()),
+            Err(nom::Err::Incomplete(_)) => Err(ParserError::nom_incomplete()),
         }
     }
 
     /// Returns nom::Err::Error as None and calls err_op.
     /// Returns nom::Err::Failure as Err.
-    /// Panics for nom::Err::Incomplete.
+    /// Returns nom::Err::Incomplete as Err with PARSE_INCOMPLETE.
     fn optional_with(
         self,
         err_op: &dyn Fn(ParserError<'s, C>),
@@ -167,8 +282,58 @@ impl<'s, C: Code> ParseAsOptional<'s, C, (Span<'s>, Option<Span<'s>>)> for Parse
                 err_op(e);
                 Ok((span, None))
             }
-            Err(nom::Err::Failure(e)) => Err(e),
-            Err(nom::Err::Incomplete(_)) => unreachable!(),
+            Err(nom::Err::Failure(e)) => Err(e.into_failure()),
+            Err(nom::Err::Incomplete(_)) => Err(ParserError::nom_incomplete()),
+        }
+    }
+
+    /// Returns nom::Err::Error as the default.
+    /// Returns nom::Err::Failure as Err.
+    /// Returns nom::Err::Incomplete as Err with PARSE_INCOMPLETE.
+    fn optional_or(self, default: Span<'s>) -> ParserResult<'s, C, (Span<'s>, Span<'s>)> {
+        match self {
+            Ok((rest, tok)) => Ok((rest, tok)),
+            Err(nom::Err::Error(e)) => Ok((e.span, default)),
+            Err(nom::Err::Failure(e)) => Err(e.into_failure()),
+            Err(nom::Err::Incomplete(_)) => Err(ParserError::nom_incomplete()),
+        }
+    }
+}
+
+/// Make an optional parse mandatory again.
+pub trait ParseAsRequired<'s, C: Code, O> {
+    /// Converts Ok with None to an Err with the given code at the rest.
+    /// For the parts that are optional in general but required in some context.
+    fn require(self, code: C) -> ParserResult<'s, C, (Span<'s>, O)>;
+}
+
+impl<'s, C: Code, O> ParseAsRequired<'s, C, O> for ParserResult<'s, C, (Span<'s>, Option<O>)> {
+    fn require(self, code: C) -> ParserResult<'s, C, (Span<'s>, O)> {
+        match self {
+            Ok((rest, Some(tok))) => Ok((rest, tok)),
+            Ok((rest, None)) => Err(ParserError::new(code, rest)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Extension for ParserResult.
+pub trait ResultExt<'s, C: Code> {
+    /// Replaces the error code, but only if it's one of the special nom codes.
+    /// A meaningful parser code is kept. into_code() always replaces the code.
+    fn or_code(self, code: C) -> Self;
+}
+
+impl<'s, C: Code, O> ResultExt<'s, C> for ParserResult<'s, C, O> {
+    fn or_code(self, code: C) -> Self {
+        match self {
+            Ok(v) => Ok(v),
+            Err(mut e) => {
+                if e.code.is_special() {
+                    e.code = code;
+                }
+                Err(e)
+            }
         }
     }
 }
@@ -200,12 +365,33 @@ pub trait Tracer<'s, C: Code> {
     /// Some detailed debug information.
     fn debug<T: Into<String>>(&mut self, step: T);
 
+    /// Attaches a key/value note to the current parser function.
+    fn note(&mut self, key: &'static str, value: String);
+
+    /// Current parser function, None before the first enter.
+    fn current(&self) -> Option<C>;
+
     /// Adds a suggestion for the current stack frame.
     fn suggest(&mut self, suggest: C, span: Span<'s>);
 
-    /// Adds a expectation for the current stack frame.
+    /// Adds an expectation for the current stack frame.
+    ///
+    /// The expectation ends up in the ParserError if the current parser fails,
+    /// the same way as a stashed error.
     fn expect(&mut self, expect: C, span: Span<'s>);
 
+    /// Skipped whitespace, comments etc. for the current stack frame.
+    ///
+    /// If the current parser fails, the trivia ends up in the ParserError
+    /// as a low priority hint.
+    fn trivia(&mut self, span: Span<'s>);
+
+    /// Marks a token that was fabricated during error recovery.
+    ///
+    /// The AST node built from the recovered region can be flagged as
+    /// synthetic, so later stages know it's not from the input.
+    fn synthetic(&mut self, code: C, span: Span<'s>);
+
     /// Keep track of this error.
     fn stash(&mut self, err: ParserError<'s, C>);
 
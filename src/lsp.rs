@@ -0,0 +1,60 @@
+//!
+//! Conversion to lsp-types for a language server.
+//!
+//! Needs the feature `lsp-types`.
+//!
+
+use crate::error::{ParserError, TextEdit};
+use crate::{Code, Span};
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+/// LSP position for a byte offset into the source.
+/// The character is counted in UTF-16 code units as LSP requires.
+pub fn to_lsp_position(offset: usize, src: &str) -> Position {
+    let offset = offset.min(src.len());
+    let before = &src[..offset];
+
+    let (line, line_start) = match before.rfind('\n') {
+        Some(idx) => (before.matches('\n').count(), idx + 1),
+        None => (0, 0),
+    };
+    let character: usize = src[line_start..offset].chars().map(char::len_utf16).sum();
+
+    Position {
+        line: line as u32,
+        character: character as u32,
+    }
+}
+
+/// LSP range of the span. The span must be part of src.
+pub fn to_lsp_range(span: Span<'_>, src: &str) -> Range {
+    let start = span.location_offset();
+    let end = start + span.len();
+    Range {
+        start: to_lsp_position(start, src),
+        end: to_lsp_position(end, src),
+    }
+}
+
+/// LSP text edit. The range of the edit must be part of src.
+pub fn to_lsp_text_edit(edit: &TextEdit, src: &str) -> lsp_types::TextEdit {
+    lsp_types::TextEdit {
+        range: Range {
+            start: to_lsp_position(edit.range.start, src),
+            end: to_lsp_position(edit.range.end, src),
+        },
+        new_text: edit.new_text.clone(),
+    }
+}
+
+impl<'s, C: Code> ParserError<'s, C> {
+    /// Diagnostic for the error. The message lists the expected codes.
+    pub fn lsp_diagnostic(&self, src: &str) -> Diagnostic {
+        Diagnostic {
+            range: to_lsp_range(self.span, src),
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: self.message(),
+            ..Default::default()
+        }
+    }
+}
@@ -26,11 +26,26 @@ impl<'s, C: Code> Tracer<'s, C> for NoTracer<'s, C> {
     /// Some detailed debug information.
     fn debug<T: Into<String>>(&mut self, _step: T) {}
 
+    /// Attaches a key/value note to the current parser function.
+    fn note(&mut self, _key: &'static str, _value: String) {}
+
+    /// Not known.
+    fn current(&self) -> Option<C> {
+        None
+    }
+
     /// Adds a suggestion for the current stack frame.
     fn suggest(&mut self, _suggest: C, _span: Span<'s>) {}
 
+    /// Adds an expectation for the current stack frame.
     fn expect(&mut self, _expect: C, _span: Span<'s>) {}
 
+    /// Trivia is not recorded.
+    fn trivia(&mut self, _span: Span<'s>) {}
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, _code: C, _span: Span<'s>) {}
+
     /// Keep track of this error.
     fn stash(&mut self, _err: ParserError<'s, C>) {}
 
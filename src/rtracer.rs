@@ -11,6 +11,9 @@ pub struct RTracer<'s, C: Code> {
 
     pub(crate) suggest: Vec<SuggestTrack<'s, C>>,
     pub(crate) expect: Vec<ExpectTrack<'s, C>>,
+
+    /// Sequence number for Expect and Suggest.
+    pub(crate) seq: u32,
 }
 
 impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
@@ -20,6 +23,7 @@ impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
             func: Vec::new(),
             suggest: Vec::new(),
             expect: Vec::new(),
+            seq: 0,
         }
     }
 
@@ -42,15 +46,30 @@ impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
         self.track_debug(step.into());
     }
 
+    /// Notes are not recorded.
+    fn note(&mut self, _key: &'static str, _value: String) {}
+
+    /// Current parser function.
+    fn current(&self) -> Option<C> {
+        self.func.last().copied()
+    }
+
     /// Adds a suggestion for the current stack frame.
     fn suggest(&mut self, suggest: C, span: Span<'s>) {
         self.add_suggest(suggest, span);
     }
 
+    /// Adds an expectation for the current stack frame.
     fn expect(&mut self, expect: C, span: Span<'s>) {
         self.add_expect(expect, span);
     }
 
+    /// Trivia is not recorded.
+    fn trivia(&mut self, _span: Span<'s>) {}
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, _code: C, _span: Span<'s>) {}
+
     /// Keep track of this error.
     fn stash(&mut self, err: ParserError<'s, C>) {
         self.add_expect(err.code, err.span);
@@ -61,6 +80,7 @@ impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
         for hint in err.hints.into_iter() {
             match hint {
                 Hints::Nom(_) => {}
+                Hints::Trivia(_) => {}
                 Hints::Suggest(v) => {
                     suggest_vec.push(v);
                 }
@@ -78,6 +98,7 @@ impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
         span: Span<'s>,
         val: T,
     ) -> ParserResult<'s, C, (Span<'s>, T)> {
+        self.check_entered();
         self.track_ok(rest, span);
 
         let expect = self.pop_expect();
@@ -99,6 +120,8 @@ impl<'s, C: Code> Tracer<'s, C> for RTracer<'s, C> {
 
     /// Write a track for an error.
     fn err<'t, T>(&'t mut self, mut err: ParserError<'s, C>) -> ParserResult<'s, C, T> {
+        self.check_entered();
+
         // Freshly created error needs to be recorded before we overwrite the code.
         if !err.tracing {
             err.tracing = true;
@@ -174,12 +197,26 @@ impl<'s, C: Code> RTracer<'s, C> {
     }
 
     fn add_expect(&mut self, code: C, span: Span<'s>) {
-        self.track_expect_single(Usage::Track, code, span);
+        let seq = self.next_seq();
+        self.track_expect_single(Usage::Track, code, span, seq);
         self.expect
             .last_mut()
             .expect("Vec<Expect> is empty")
             .list
-            .push(Expect { code, span })
+            .push(Expect {
+                code,
+                span,
+                related: None,
+                seq,
+            })
+    }
+}
+
+// sequence
+impl<'s, C: Code> RTracer<'s, C> {
+    fn next_seq(&mut self) -> u32 {
+        self.seq += 1;
+        self.seq
     }
 }
 
@@ -198,11 +235,17 @@ impl<'s, C: Code> RTracer<'s, C> {
     }
 
     fn add_suggest(&mut self, code: C, span: Span<'s>) {
+        let seq = self.next_seq();
         self.suggest
             .last_mut()
             .expect("Vec<Suggest> is empty")
             .list
-            .push(Suggest { code, span })
+            .push(Suggest {
+                code,
+                span,
+                related: None,
+                seq,
+            })
     }
 
     fn append_suggest(&mut self, mut suggest: Vec<Suggest<'s, C>>) {
@@ -225,6 +268,15 @@ impl<'s, C: Code> RTracer<'s, C> {
     fn pop_func(&mut self) {
         self.func.pop();
     }
+
+    // ok/err without a matching enter.
+    // This usually ends with an empty stack at the outermost parser.
+    fn check_entered(&self) {
+        debug_assert!(
+            !self.func.is_empty(),
+            "trace.ok() or trace.err() without trace.enter(). forgot to call trace.enter()?"
+        );
+    }
 }
 
 // basic tracking
@@ -239,7 +291,7 @@ impl<'s, C: Code> RTracer<'s, C> {
 
     fn track_expect(&self, _usage: Usage, _expect: Cow<Vec<Expect<'s, C>>>) {}
 
-    fn track_expect_single(&mut self, _usage: Usage, _code: C, _span: Span<'s>) {}
+    fn track_expect_single(&mut self, _usage: Usage, _code: C, _span: Span<'s>, _seq: u32) {}
 
     fn track_ok(&self, _rest: Span<'s>, _span: Span<'s>) {}
 
@@ -7,6 +7,8 @@ extern crate memchr;
 
 use crate::Span;
 use nom::Offset;
+use std::cmp::{max, min};
+use std::ops::Range;
 use std::slice;
 use std::str::from_utf8_unchecked;
 
@@ -19,11 +21,25 @@ pub fn span_union_opt<'a>(span0: Option<Span<'a>>, span1: Span<'a>) -> Span<'a>
     }
 }
 
-/// Returns n lines before and after the current line if available.
-pub fn get_lines_around(span0: Span<'_>, n: u32) -> Vec<Span<'_>> {
-    let mut lines = get_lines_before(span0, n);
+/// Returns the byte range of the span.
+pub fn span_range(span0: Span<'_>) -> Range<usize> {
+    span0.location_offset()..span0.location_offset() + span0.fragment().len()
+}
+
+/// Returns the byte range from the start of the first to the end of the last span.
+pub fn span_range_union(span0: Span<'_>, span1: Span<'_>) -> Range<usize> {
+    let range0 = span_range(span0);
+    let range1 = span_range(span1);
+    min(range0.start, range1.start)..max(range0.end, range1.end)
+}
+
+/// Returns some lines before and after the current line if available.
+/// The lines are in source order, the current line is contained once.
+pub fn get_lines_around(span0: Span<'_>, before: u32, after: u32) -> Vec<Span<'_>> {
+    let mut lines = get_lines_before(span0, before);
+    // current line is the last one, and the first one for get_lines_after.
     lines.pop();
-    lines.append(&mut get_lines_after(span0, n));
+    lines.append(&mut get_lines_after(span0, after));
 
     lines
 }
@@ -70,6 +86,10 @@ pub fn get_lines_after(span0: Span<'_>, n: u32) -> Vec<Span<'_>> {
 
     if loop_slice.len() > 0 {
         for i in 1..=n {
+            if loop_slice.is_empty() {
+                // the last line ended with a \n, there is nothing after it.
+                break;
+            }
             (loop_slice, loop_offset) = match memchr::memchr(b'\n', loop_slice) {
                 None => {
                     // at end
@@ -162,7 +182,19 @@ pub fn get_lines_before(span0: Span<'_>, n: u32) -> Vec<Span<'_>> {
     r
 }
 
-#[allow(dead_code)]
+/// Returns the original source from its start up to the end of the span.
+/// Nothing beyond the end of the span is included.
+///
+/// Useful for diagnostics when only the span of an error is at hand.
+///
+/// Note: The span must originate from a single Span::new(), ie. it must be
+/// derived via nom or span_union() from one contiguous original str.
+/// A Span created with Span::new_from_raw_offset() with an offset that
+/// doesn't match the original leads to Undefined Behavior.
+pub fn source_up_to(span0: Span<'_>) -> &str {
+    get_unoffsetted_span(span0).fragment()
+}
+
 fn get_unoffsetted_span(span0: Span<'_>) -> Span<'_> {
     unsafe {
         let slice = get_unoffsetted_slice(span0);
@@ -236,16 +268,149 @@ pub fn span_union<'a>(span0: Span<'a>, span1: Span<'a>) -> Span<'a> {
     }
 }
 
+/// Is the inner span completely contained in the outer span?
+///
+/// Spans from different inputs are never contained.
+pub fn span_contains(outer: Span<'_>, inner: Span<'_>) -> bool {
+    if get_unoffsetted_ptr(outer) != get_unoffsetted_ptr(inner) {
+        return false;
+    }
+
+    let outer = span_range(outer);
+    let inner = span_range(inner);
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+/// Returns the overlapping part of both spans.
+///
+/// Returns None if the spans don't overlap or are from different inputs.
+/// Adjacent spans and empty spans have no overlap.
+pub fn span_intersect<'a>(span0: Span<'a>, span1: Span<'a>) -> Option<Span<'a>> {
+    if get_unoffsetted_ptr(span0) != get_unoffsetted_ptr(span1) {
+        return None;
+    }
+
+    let range0 = span_range(span0);
+    let range1 = span_range(span1);
+    let start = max(range0.start, range1.start);
+    let end = min(range0.end, range1.end);
+    if start >= end {
+        return None;
+    }
+
+    // the later span starts the intersection.
+    let first = if span0.location_offset() >= span1.location_offset() {
+        span0
+    } else {
+        span1
+    };
+
+    unsafe {
+        // both spans are from the same original, and start..end is part of both.
+        let self_ptr = first.fragment().as_ptr();
+        let slice = slice::from_raw_parts(self_ptr, end - start);
+
+        // start and end are on char boundaries of valid spans.
+        let str = from_utf8_unchecked(slice);
+
+        Some(Span::new_from_raw_offset(
+            first.location_offset(),
+            first.location_line(),
+            str,
+            (),
+        ))
+    }
+}
+
+/// Removes leading and trailing ASCII whitespace from the span.
+pub fn span_trim(span0: Span<'_>) -> Span<'_> {
+    span_trim_end(span_trim_start(span0))
+}
+
+/// Removes leading ASCII whitespace from the span.
+/// Offset and line are adjusted for the removed prefix.
+pub fn span_trim_start(span0: Span<'_>) -> Span<'_> {
+    let frag = *span0.fragment();
+    let trimmed = frag.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let prefix = &frag[..frag.len() - trimmed.len()];
+    let lines = memchr::memchr_iter(b'\n', prefix.as_bytes()).count() as u32;
+
+    unsafe {
+        // trimmed is part of the fragment, so offset and line are still valid.
+        Span::new_from_raw_offset(
+            span0.location_offset() + prefix.len(),
+            span0.location_line() + lines,
+            trimmed,
+            (),
+        )
+    }
+}
+
+/// Removes trailing ASCII whitespace from the span.
+pub fn span_trim_end(span0: Span<'_>) -> Span<'_> {
+    let trimmed = span0
+        .fragment()
+        .trim_end_matches(|c: char| c.is_ascii_whitespace());
+
+    unsafe {
+        // same start, only shorter.
+        Span::new_from_raw_offset(span0.location_offset(), span0.location_line(), trimmed, ())
+    }
+}
+
+/// Line start offsets of a source for repeated line/column lookups.
+///
+/// Build it once and use it for all the errors of the same source.
+/// Lines are 1-based like location_line(), columns are 1-based byte
+/// columns like get_column().
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Offset of the first byte of each line.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Collects the line starts of the source.
+    pub fn new(src: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(memchr::memchr_iter(b'\n', src.as_bytes()).map(|v| v + 1));
+        Self { starts }
+    }
+
+    /// Number of lines.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Line and column for the byte offset.
+    pub fn line_col(&self, offset: usize) -> (u32, usize) {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        ((line + 1) as u32, offset - self.starts[line] + 1)
+    }
+
+    /// Line and column for the start of the span.
+    pub fn span_line_col(&self, span: Span<'_>) -> (u32, usize) {
+        self.line_col(span.location_offset())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::error::ParserError;
-    use crate::span::{get_lines_after, get_lines_before, span_union};
+    use crate::span::{
+        get_lines_after, get_lines_around, get_lines_before, source_up_to, span_contains,
+        span_intersect, span_range, span_range_union, span_trim, span_trim_end, span_trim_start,
+        span_union, LineIndex,
+    };
     use crate::{Code, ParserNomResult, Span};
     use nom::bytes::complete::{take_while, take_while1};
     use nom::character::complete::digit1;
     use nom::combinator::recognize;
     use nom::sequence::preceded;
-    use nom::InputTakeAtPosition;
+    use nom::{InputTake, InputTakeAtPosition};
     use std::fmt::{Debug, Display, Formatter};
 
     #[test]
@@ -272,6 +437,44 @@ mod tests {
         dbg!(get_lines_after(s0, 2));
     }
 
+    #[test]
+    pub fn test_lines_after_eof() {
+        let span0 = Span::new("a\nb");
+        let (s0, _s1) = span0
+            .split_at_position::<_, nom::error::Error<Span<'_>>>(|c| c == 'b')
+            .unwrap();
+
+        for n in 0..=2 {
+            let lines = get_lines_after(s0, n);
+            let lines: Vec<_> = lines
+                .iter()
+                .map(|v| (v.location_line(), v.location_offset(), *v.fragment()))
+                .collect();
+            assert_eq!(lines, vec![(2, 2, "b")]);
+        }
+
+        let (s0, _s1) = span0
+            .split_at_position::<_, nom::error::Error<Span<'_>>>(|c| c == '\n')
+            .unwrap();
+        let lines = get_lines_after(s0, 2);
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|v| (v.location_line(), v.location_offset(), *v.fragment()))
+            .collect();
+        assert_eq!(lines, vec![(1, 0, "a"), (2, 2, "b")]);
+
+        let span0 = Span::new("a\nb\n");
+        let (s0, _s1) = span0
+            .split_at_position::<_, nom::error::Error<Span<'_>>>(|c| c == '\n')
+            .unwrap();
+        let lines = get_lines_after(s0, 2);
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|v| (v.location_line(), v.location_offset(), *v.fragment()))
+            .collect();
+        assert_eq!(lines, vec![(1, 0, "a"), (2, 2, "b")]);
+    }
+
     #[test]
     pub fn test_lines_before() {
         let span0 = Span::new("1234\n5678\nabcd\nefgh\n");
@@ -289,6 +492,52 @@ mod tests {
         dbg!(get_lines_before(s1, 4));
     }
 
+    #[test]
+    pub fn test_lines_around() {
+        let span0 = Span::new("1234\n5678\nabcd\nefgh\n");
+        let (s0, _s1) = span0
+            .split_at_position::<_, nom::error::Error<Span<'_>>>(|c| c == 'c')
+            .unwrap();
+
+        let lines = get_lines_around(s0, 1, 1);
+        let lines: Vec<_> = lines.iter().map(|v| *v.fragment()).collect();
+        assert_eq!(lines, vec!["5678", "abcd", "efgh"]);
+
+        let lines = get_lines_around(s0, 0, 0);
+        let lines: Vec<_> = lines.iter().map(|v| *v.fragment()).collect();
+        assert_eq!(lines, vec!["abcd"]);
+    }
+
+    #[test]
+    pub fn test_span_range() {
+        let span = Span::new("abcdef");
+        assert_eq!(span_range(span), 0..6);
+
+        let (rest, tok) = span.take_split(2);
+        assert_eq!(span_range(tok), 0..2);
+        assert_eq!(span_range(rest), 2..6);
+
+        // empty span at the end
+        let (rest, _) = span.take_split(6);
+        assert_eq!(span_range(rest), 6..6);
+
+        // byte range, not chars
+        let span = Span::new("äöü!");
+        let (rest, _) = span.take_split(4);
+        assert_eq!(span_range(rest), 4..7);
+    }
+
+    #[test]
+    pub fn test_span_range_union() {
+        let span = Span::new("abcdef");
+        let (rest, tok0) = span.take_split(1);
+        let (rest, _) = rest.take_split(2);
+        let (_, tok1) = rest.take_split(1);
+
+        assert_eq!(span_range_union(tok0, tok1), 0..4);
+        assert_eq!(span_range_union(tok1, tok0), 0..4);
+    }
+
     #[test]
     pub fn test_union_ok() {
         let span = Span::new("1234 test");
@@ -300,6 +549,68 @@ mod tests {
         span_union(number, name);
     }
 
+    #[test]
+    pub fn test_trim() {
+        let span = Span::new("x \n\t abc  \n");
+        let (rest, _) = nom_name(span).unwrap();
+
+        let t = span_trim(rest);
+        assert_eq!(*t.fragment(), "abc");
+        assert_eq!(t.location_offset(), 5);
+        assert_eq!(t.location_line(), 2);
+        assert_eq!(t.get_column(), 3);
+
+        let t = span_trim_start(rest);
+        assert_eq!(*t.fragment(), "abc  \n");
+        assert_eq!(t.location_offset(), 5);
+
+        let t = span_trim_end(rest);
+        assert_eq!(*t.fragment(), " \n\t abc");
+        assert_eq!(t.location_offset(), 1);
+        assert_eq!(t.location_line(), 1);
+
+        let t = span_trim(Span::new("   "));
+        assert_eq!(*t.fragment(), "");
+        assert_eq!(t.location_offset(), 3);
+    }
+
+    #[test]
+    pub fn test_line_index() {
+        let src = "12\nabc\n\nx";
+        let idx = LineIndex::new(src);
+        assert_eq!(idx.line_count(), 4);
+        assert_eq!(idx.line_col(0), (1, 1));
+        assert_eq!(idx.line_col(2), (1, 3));
+        assert_eq!(idx.line_col(3), (2, 1));
+        assert_eq!(idx.line_col(5), (2, 3));
+        assert_eq!(idx.line_col(7), (3, 1));
+        assert_eq!(idx.line_col(8), (4, 1));
+        assert_eq!(idx.line_col(9), (4, 2));
+
+        let span = Span::new(src);
+        let (rest, _) = nom_number(span).unwrap();
+        let (rest, _) = take_while::<_, _, ParserError<'_, TCode>>(|c| c == '\n')(rest).unwrap();
+        assert_eq!(
+            idx.span_line_col(rest),
+            (rest.location_line(), rest.get_column())
+        );
+    }
+
+    #[test]
+    pub fn test_source_up_to() {
+        let span = Span::new("1234 test");
+
+        let (rest, number) = nom_number(span).unwrap();
+        let (_rest, name) = nom_name(rest).unwrap();
+
+        assert_eq!(source_up_to(number), "1234");
+        assert_eq!(source_up_to(name), "1234 test");
+
+        let union = span_union(number, name);
+        assert_eq!(source_up_to(union), "1234 test");
+        assert_eq!(source_up_to(span), "1234 test");
+    }
+
     #[test]
     #[should_panic]
     pub fn test_union_order() {
@@ -336,6 +647,27 @@ mod tests {
         span_union(name, other);
     }
 
+    #[test]
+    pub fn test_contains_intersect() {
+        let span = Span::new("1234 test");
+        let other = Span::new("5678 xxxx");
+
+        let (rest, number) = nom_number(span).unwrap();
+        let (_rest, name) = nom_name(rest).unwrap();
+
+        assert!(span_contains(span, number));
+        assert!(span_contains(span, name));
+        assert!(!span_contains(number, name));
+        assert!(!span_contains(other, number));
+
+        assert!(span_intersect(number, name).is_none());
+        assert!(span_intersect(other, number).is_none());
+
+        let isect = span_intersect(span, name).unwrap();
+        assert_eq!(*isect.fragment(), "test");
+        assert_eq!(isect.location_offset(), 5);
+    }
+
     #[allow(dead_code)]
     #[derive(Debug, PartialEq, Eq, Clone, Copy)]
     enum TCode {
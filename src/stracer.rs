@@ -0,0 +1,228 @@
+use crate::debug::tracer::debug_track_line;
+use crate::error::{DebugWidth, Expect, ParserError, Suggest};
+use crate::etracer::ErrorTracer;
+use crate::tracer::{
+    DebugTrack, EnterTrack, ErrTrack, ExpectTrack, NoteTrack, OkTrack, StepTrack, SuggestTrack,
+    SyntheticTrack, Track, Usage,
+};
+use crate::{Code, ParserResult, Span, Tracer};
+use std::io;
+
+/// Tracer that writes each track immediately and keeps nothing.
+///
+/// The output is the same as the trace of CTracer, line by line. The expect
+/// and suggest values are still collected like ErrorTracer.
+///
+/// Tracer::new() has no writer and discards the output. Use with_writer().
+/// Write errors stop the output, the first one is kept for io_error().
+pub struct StreamTracer<'s, C: Code, W: io::Write> {
+    out: Option<W>,
+    w: DebugWidth,
+    func: Vec<C>,
+    func_offset: Vec<usize>,
+    errors: ErrorTracer<'s, C>,
+    io_error: Option<io::Error>,
+}
+
+impl<'s, C: Code, W: io::Write> Tracer<'s, C> for StreamTracer<'s, C, W> {
+    /// New one without a writer.
+    fn new() -> Self {
+        Self {
+            out: None,
+            w: DebugWidth::Medium,
+            func: Vec::new(),
+            func_offset: Vec::new(),
+            errors: ErrorTracer::new(),
+            io_error: None,
+        }
+    }
+
+    /// Enter a parser function. Absolutely necessary for the rest.
+    fn enter(&mut self, func: C, span: Span<'s>) {
+        self.func.push(func);
+        self.func_offset.push(span.location_offset());
+        self.errors.enter(func, span);
+
+        self.write_track(Track::Enter(EnterTrack {
+            func,
+            span,
+            parents: self.func.clone(),
+        }));
+    }
+
+    /// Keep track of steps in a complicated parser.
+    fn step(&mut self, step: &'static str, span: Span<'s>) {
+        self.write_track(Track::Step(StepTrack {
+            func: self.func(),
+            step,
+            span,
+            parents: self.func.clone(),
+        }));
+    }
+
+    /// Some detailed debug information.
+    fn debug<T: Into<String>>(&mut self, step: T) {
+        self.write_track(Track::Debug(DebugTrack {
+            func: self.func(),
+            dbg: step.into(),
+            parents: self.func.clone(),
+            _phantom: Default::default(),
+        }));
+    }
+
+    /// Attaches a key/value note to the current parser function.
+    fn note(&mut self, key: &'static str, value: String) {
+        self.write_track(Track::Note(NoteTrack {
+            func: self.func(),
+            key,
+            value,
+            parents: self.func.clone(),
+            _phantom: Default::default(),
+        }));
+    }
+
+    /// Current parser function.
+    fn current(&self) -> Option<C> {
+        self.func.last().copied()
+    }
+
+    /// Adds a suggestion for the current stack frame.
+    fn suggest(&mut self, suggest: C, span: Span<'s>) {
+        self.errors.suggest(suggest, span);
+    }
+
+    /// Adds an expectation for the current stack frame.
+    fn expect(&mut self, expect: C, span: Span<'s>) {
+        self.errors.expect(expect, span);
+    }
+
+    /// Skipped trivia for the current stack frame.
+    fn trivia(&mut self, span: Span<'s>) {
+        self.errors.trivia(span);
+    }
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, code: C, span: Span<'s>) {
+        self.write_track(Track::Synthetic(SyntheticTrack {
+            func: self.func(),
+            code,
+            span,
+            parents: self.func.clone(),
+        }));
+    }
+
+    /// Keep track of this error.
+    fn stash(&mut self, err: ParserError<'s, C>) {
+        self.errors.stash(err);
+    }
+
+    /// Write a track for an ok result.
+    fn ok<'t, T>(
+        &'t mut self,
+        rest: Span<'s>,
+        span: Span<'s>,
+        val: T,
+    ) -> ParserResult<'s, C, (Span<'s>, T)> {
+        self.write_track(Track::Ok(OkTrack {
+            func: self.func(),
+            span,
+            rest,
+            parents: self.func.clone(),
+        }));
+        self.write_expect(Usage::Drop);
+
+        self.func.pop();
+        self.func_offset.pop();
+        self.errors.ok(rest, span, val)
+    }
+
+    /// Write a track for an error.
+    fn err<'t, T>(&'t mut self, err: ParserError<'s, C>) -> ParserResult<'s, C, T> {
+        self.write_expect(Usage::Use);
+        self.write_suggest(Usage::Use);
+        let consumed = match self.func_offset.last() {
+            Some(offset) => err.span.location_offset().saturating_sub(*offset),
+            None => 0,
+        };
+        self.write_track(Track::Err(ErrTrack {
+            func: self.func(),
+            span: err.span,
+            err: err.to_string(),
+            backtracked: consumed > 0,
+            consumed,
+            parents: self.func.clone(),
+        }));
+
+        self.func.pop();
+        self.func_offset.pop();
+        self.errors.err(err)
+    }
+}
+
+impl<'s, C: Code, W: io::Write> StreamTracer<'s, C, W> {
+    /// New one that writes to the given writer.
+    pub fn with_writer(out: W, w: DebugWidth) -> Self {
+        let mut trace = Self::new();
+        trace.out = Some(out);
+        trace.w = w;
+        trace
+    }
+
+    /// First write error.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io_error.as_ref()
+    }
+
+    /// Returns the writer.
+    pub fn into_writer(self) -> Option<W> {
+        self.out
+    }
+
+    /// Remaining expect and suggest values.
+    pub fn to_results(&mut self) -> (Vec<Expect<'s, C>>, Vec<Suggest<'s, C>>) {
+        self.errors.to_results()
+    }
+
+    fn func(&self) -> C {
+        *self
+            .func
+            .last()
+            .expect("Vec<FnCode> is empty. forgot to trace.enter()")
+    }
+
+    // Expect values of the current frame, before they are used or dropped.
+    fn write_expect(&mut self, usage: Usage) {
+        let func = self.func();
+        if let Some(list) = self.errors.expect.last() {
+            if !list.is_empty() {
+                let list = list.clone();
+                self.write_track(Track::Expect(ExpectTrack { func, usage, list }));
+            }
+        }
+    }
+
+    // Suggest values of the current frame, before they are used.
+    fn write_suggest(&mut self, usage: Usage) {
+        let func = self.func();
+        if let Some(list) = self.errors.suggest.last() {
+            if !list.is_empty() {
+                let list = list.clone();
+                self.write_track(Track::Suggest(SuggestTrack { func, usage, list }));
+            }
+        }
+    }
+
+    fn write_track(&mut self, t: Track<'s, C>) {
+        if self.io_error.is_some() {
+            return;
+        }
+        if let Some(out) = &mut self.out {
+            let mut line = String::new();
+            // writing to a String doesn't fail.
+            let _ = debug_track_line(&mut line, self.w, self.func.len(), &t);
+            if let Err(e) = out.write_all(line.as_bytes()) {
+                self.io_error = Some(e);
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::error::DebugWidth;
+use crate::tracer::CTracer;
+use crate::{Code, FilterFn};
+use std::fmt;
+use std::fmt::Debug;
+use std::sync::{Mutex, PoisonError};
+
+/// Collects the CTracer of several parsers running in parallel.
+///
+/// CTracer is Send as long as the Code is, so each worker thread can own its
+/// own tracer. The Span<'s> only borrows the input, so the input must outlive
+/// the workers, e.g. by using scoped threads.
+///
+/// When a worker is done it hands over its tracer with merge(), keyed by some
+/// section id.
+pub struct SyncTracer<'s, C: Code, K, const TRACK: bool = true> {
+    sections: Mutex<Vec<(K, CTracer<'s, C, TRACK>)>>,
+}
+
+impl<'s, C: Code, K, const TRACK: bool> SyncTracer<'s, C, K, TRACK> {
+    /// New one.
+    pub fn new() -> Self {
+        Self {
+            sections: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds the tracer of one finished section.
+    pub fn merge(&self, section: K, trace: CTracer<'s, C, TRACK>) {
+        self.sections
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((section, trace));
+    }
+
+    /// Returns all collected tracers in the order they were merged.
+    pub fn into_sections(self) -> Vec<(K, CTracer<'s, C, TRACK>)> {
+        self.sections
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<'s, C: Code, K, const TRACK: bool> Default for SyncTracer<'s, C, K, TRACK> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// output
+impl<'s, C: Code, K: Debug, const TRACK: bool> SyncTracer<'s, C, K, TRACK> {
+    /// Write a debug output of all sections.
+    pub fn write(
+        &self,
+        out: &mut impl fmt::Write,
+        w: DebugWidth,
+        filter: FilterFn<'_, C>,
+    ) -> fmt::Result {
+        let sections = self.sections.lock().unwrap_or_else(PoisonError::into_inner);
+        for (section, trace) in sections.iter() {
+            writeln!(out, "section {:?}", section)?;
+            trace.write(out, w, filter)?;
+        }
+        Ok(())
+    }
+}
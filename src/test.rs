@@ -2,13 +2,15 @@ use crate::debug::restrict;
 use crate::error::{DebugWidth, ParserError};
 use crate::notracer::NoTracer;
 use crate::rtracer::RTracer;
-use crate::tracer::CTracer;
+use crate::tracer::{CTracer, Track};
 use crate::{Code, FilterFn, ParserResult, Span, Tracer};
 use ::nom::IResult;
 use std::cell::{Cell, RefCell};
 use std::fmt;
 use std::fmt::Debug;
+use std::fs;
 use std::marker::PhantomData;
+use std::path::Path;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -18,6 +20,9 @@ pub type TestedFn<I, O, E> = fn(I) -> Result<O, E>;
 /// Value comparison.
 pub type CompareFn<O, V> = for<'a> fn(&'a O, V) -> bool;
 
+/// Compare function for the Test that can explain the mismatch.
+pub type CompareFnMsg<O, V> = for<'a> fn(&'a O, V) -> Result<(), String>;
+
 /// Signature of a classic nom function for Test.
 pub type NomFn<'s, O> = fn(Span<'s>) -> IResult<Span<'s>, O>;
 
@@ -101,6 +106,31 @@ macro_rules! optional {
     };
 }
 
+/// Runs test_parse() for a list of inputs with the same parser and report.
+///
+/// Each entry is a tuple of the input, the expected rest and any number of
+/// additional checks. The checks are method calls on Test.
+///
+/// '''
+/// parse_tests!(ParseNumber::parse, &CheckTrace(DebugWidth::Short);
+///     ("12", ""),
+///     ("12 ", " ", ok(cmp_number, 12)),
+/// );
+/// '''
+///
+#[allow(unused_macros)]
+#[macro_export]
+macro_rules! parse_tests {
+    ($parser:expr, $report:expr; $( ($input:expr, $rest:expr $(, $check:ident ( $($arg:expr),* ))* ) ),* $(,)?) => {
+        $(
+            $crate::test::test_parse($input, $parser)
+                .rest($rest)
+                $( .$check( $($arg),* ) )*
+                .q($report);
+        )*
+    };
+}
+
 // General stuff ---------------------------------------------------------
 
 /// Run a test for a nom parser.
@@ -180,8 +210,14 @@ pub fn test_parse<'a, 's, V: Debug, C: Code>(
     span: &'s str,
     fn_test: ParserFn<'s, V, C, true>,
 ) -> Test<TestTracer<'a, 's, C, true>, Span<'s>, (Span<'s>, V), ParserError<'s, C>> {
-    let span = Span::new(span);
+    test_parse_span(Span::new(span), fn_test)
+}
 
+// test_parse with a span.
+fn test_parse_span<'a, 's, V: Debug, C: Code>(
+    span: Span<'s>,
+    fn_test: ParserFn<'s, V, C, true>,
+) -> Test<TestTracer<'a, 's, C, true>, Span<'s>, (Span<'s>, V), ParserError<'s, C>> {
     let mut trace: CTracer<C, true> = CTracer::new();
     let now = Instant::now();
     let result = fn_test(&mut trace, span);
@@ -199,6 +235,32 @@ pub fn test_parse<'a, 's, V: Debug, C: Code>(
     }
 }
 
+/// Runs the parser with the content of the file and records the results.
+///
+/// The content is leaked to satisfy the lifetime of the Span. This is
+/// acceptable for a test.
+///
+/// Panics if the file can't be read.
+#[must_use]
+#[track_caller]
+pub fn test_parse_file<'a, V: Debug, C: Code>(
+    path: &Path,
+    fn_test: ParserFn<'static, V, C, true>,
+) -> Test<
+    TestTracer<'a, 'static, C, true>,
+    Span<'static>,
+    (Span<'static>, V),
+    ParserError<'static, C>,
+> {
+    let txt = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) => panic!("can't read {:?}: {}", path, e),
+    };
+    let txt: &'static str = Box::leak(txt.into_boxed_str());
+
+    test_parse(txt, fn_test)
+}
+
 #[must_use]
 pub fn test_parse_false<'a, 's, V: Debug, C: Code>(
     span: &'s str,
@@ -295,6 +357,11 @@ where
         self.fail.set(true);
     }
 
+    /// Duration of the parser run.
+    pub fn elapsed(&self) -> Duration {
+        self.duration
+    }
+
     /// Always fails.
     ///
     /// Finish the test with q().
@@ -376,6 +443,32 @@ where
         self
     }
 
+    /// Checks for ok.
+    /// Uses a compare function that returns a message for a mismatch.
+    ///
+    /// Finish the test with q()
+    #[must_use]
+    pub fn ok_msg<V>(&self, eq: CompareFnMsg<O, V>, test: V) -> &Self
+    where
+        V: Debug + Copy,
+        O: Debug,
+    {
+        match &self.result {
+            Ok((_, token)) => {
+                if let Err(msg) = eq(token, test) {
+                    println!("FAIL: Value mismatch: {}", msg);
+                    println!("    {:?} <> {:?}", token, test);
+                    self.flag_fail();
+                }
+            }
+            Err(_) => {
+                println!("FAIL: Expect ok, but was an error!");
+                self.flag_fail();
+            }
+        }
+        self
+    }
+
     /// Tests the remaining string after parsing.
     ///
     /// Finish the test with q()
@@ -399,6 +492,24 @@ where
         }
         self
     }
+
+    /// Same as ok(), but only returns the result.
+    /// Doesn't print anything and doesn't set the failed flag.
+    pub fn check_ok<V>(&self, eq: CompareFn<O, V>, test: V) -> bool {
+        match &self.result {
+            Ok((_, token)) => eq(token, test),
+            Err(_) => false,
+        }
+    }
+
+    /// Same as rest(), but only returns the result.
+    /// Doesn't print anything and doesn't set the failed flag.
+    pub fn check_rest(&self, test: &str) -> bool {
+        match &self.result {
+            Ok((rest, _)) => **rest == test,
+            Err(_) => false,
+        }
+    }
 }
 
 // Span based ------------------------------------------------------------
@@ -564,6 +675,147 @@ where
         self.x.trace_filter.replace(filter);
         self
     }
+
+    /// Compares the rendered trace with DebugWidth::Medium.
+    /// Trailing whitespace is ignored for each line.
+    ///
+    /// Finish the test with q()
+    #[must_use]
+    pub fn trace_eq(&self, expected: &str) -> &Self {
+        let mut buf = String::new();
+        let filter = self.x.trace_filter.borrow();
+        if self
+            .x
+            .trace
+            .write(&mut buf, DebugWidth::Medium, *filter)
+            .is_err()
+        {
+            println!("FAIL: Can't write the trace.");
+            self.flag_fail();
+            return self;
+        }
+
+        let result: Vec<_> = buf.lines().map(|v| v.trim_end()).collect();
+        let expected: Vec<_> = expected.lines().map(|v| v.trim_end()).collect();
+
+        if result != expected {
+            println!("FAIL: Trace mismatch:");
+            for i in 0..result.len().max(expected.len()) {
+                let r = result.get(i).copied().unwrap_or("");
+                let e = expected.get(i).copied().unwrap_or("");
+                if r != e {
+                    println!("    {}: -{}", i + 1, e);
+                    println!("    {}: +{}", i + 1, r);
+                }
+            }
+            self.flag_fail();
+        }
+        self
+    }
+
+    /// Runs the next parser with the rest of this one.
+    /// Returns a new Test for the second parser.
+    ///
+    /// If a check of this parser failed, its trace is printed right away
+    /// and the failure is carried over to the new Test.
+    ///
+    /// Panics if this parser returned an error.
+    #[must_use]
+    #[track_caller]
+    pub fn then_parse<'b, V: Debug>(
+        &self,
+        fn_test: ParserFn<'s, V, C, true>,
+    ) -> Test<TestTracer<'b, 's, C, true>, Span<'s>, (Span<'s>, V), ParserError<'s, C>> {
+        let next = match &self.result {
+            Ok((rest, _)) => test_parse_span(*rest, fn_test),
+            Err(e) => panic!("then_parse: the first parser failed with {:?}", e),
+        };
+        if self.fail.get() {
+            trace(self, DebugWidth::Long);
+            next.flag_fail();
+        }
+        next
+    }
+
+    /// Runs the parser again with the same input and compares with the
+    /// first run. The Ok/Err, the rest or the error code and offset and the
+    /// number of tracks must be the same.
+    ///
+    /// A mismatch usually means some hidden mutable state in the parser.
+    ///
+    /// Finish the test with q()
+    #[must_use]
+    pub fn assert_idempotent(&self, fn_test: ParserFn<'s, O, C, TRACK>) -> &Self {
+        let mut trace: CTracer<C, TRACK> = CTracer::new();
+        let result = fn_test(&mut trace, self.span);
+
+        match (&self.result, &result) {
+            (Ok((rest, _)), Ok((rest2, _))) => {
+                if rest.location_offset() != rest2.location_offset() {
+                    println!(
+                        "FAIL: Not idempotent, rest at {} and then at {}",
+                        rest.location_offset(),
+                        rest2.location_offset()
+                    );
+                    self.flag_fail();
+                }
+            }
+            (Err(e), Err(e2)) => {
+                if e.code != e2.code || e.span.location_offset() != e2.span.location_offset() {
+                    println!(
+                        "FAIL: Not idempotent, error {} at {} and then {} at {}",
+                        e.code,
+                        e.span.location_offset(),
+                        e2.code,
+                        e2.span.location_offset()
+                    );
+                    self.flag_fail();
+                }
+            }
+            (Ok(_), Err(e2)) => {
+                println!("FAIL: Not idempotent, ok and then error {:?}", e2);
+                self.flag_fail();
+            }
+            (Err(e), Ok(_)) => {
+                println!("FAIL: Not idempotent, error {:?} and then ok", e);
+                self.flag_fail();
+            }
+        }
+
+        if self.x.trace.tracks().len() != trace.tracks().len() {
+            println!(
+                "FAIL: Not idempotent, {} tracks and then {} tracks",
+                self.x.trace.tracks().len(),
+                trace.tracks().len()
+            );
+            self.flag_fail();
+        }
+
+        self
+    }
+
+    /// Checks that the parser function was entered during the parse.
+    ///
+    /// Finish the test with q()
+    #[must_use]
+    pub fn entered(&self, code: C) -> &Self {
+        let entered: Vec<_> = self
+            .x
+            .trace
+            .tracks()
+            .iter()
+            .filter_map(|v| match v {
+                Track::Enter(v) => Some(v.func),
+                _ => None,
+            })
+            .collect();
+
+        if !entered.contains(&code) {
+            println!("FAIL: {} was not entered: {:?}", code, entered);
+            self.flag_fail();
+        }
+        self
+    }
 }
 
 /// Extra data for the parser fn.
@@ -579,7 +831,7 @@ pub struct TestNoTracer<'s, C: Code> {
 // Reporting -------------------------------------------------------------
 
 /// Dumps the Result data if any test failed.
-pub struct CheckDump;
+pub struct CheckDump(pub DebugWidth);
 
 impl<'s, P, O, E> Report<Test<P, Span<'s>, (Span<'s>, O), E>> for CheckDump
 where
@@ -589,12 +841,24 @@ where
     #[track_caller]
     fn report(&self, test: &Test<P, Span<'s>, (Span<'s>, O), E>) {
         if test.fail.get() {
-            dump(test);
+            dump(test, self.0);
             panic!("test failed")
         }
     }
 }
 
+/// Calls a user function with the test.
+/// Eg to feed the timings into something else.
+///
+/// The closure needs the type of the argument: `|t: &Test<_, _, _, _>| ...`
+pub struct Custom<T>(pub Box<dyn Fn(&T)>);
+
+impl<T> Report<T> for Custom<T> {
+    fn report(&self, test: &T) {
+        (self.0)(test)
+    }
+}
+
 /// Dumps the Result data.
 pub struct Timing(pub u32);
 
@@ -625,7 +889,7 @@ where
 }
 
 /// Dumps the Result data.
-pub struct Dump;
+pub struct Dump(pub DebugWidth);
 
 impl<'s, P, O, E> Report<Test<P, Span<'s>, (Span<'s>, O), E>> for Dump
 where
@@ -633,11 +897,11 @@ where
     O: Debug,
 {
     fn report(&self, test: &Test<P, Span<'s>, (Span<'s>, O), E>) {
-        dump(test)
+        dump(test, self.0)
     }
 }
 
-fn dump<'s, P, O, E>(test: &Test<P, Span<'s>, (Span<'s>, O), E>)
+fn dump<'s, P, O, E>(test: &Test<P, Span<'s>, (Span<'s>, O), E>, w: DebugWidth)
 where
     E: Debug,
     O: Debug,
@@ -645,7 +909,7 @@ where
     println!();
     println!(
         "when parsing '{}' in {} =>",
-        restrict(DebugWidth::Medium, test.span),
+        restrict(w, test.span),
         humantime::format_duration(test.duration)
     );
     match &test.result {
@@ -655,13 +919,16 @@ where
         }
         Err(e) => {
             println!("error");
-            println!("{:1?}", e);
+            println!("{:1$?}", e, usize::from(w));
         }
     }
 }
 
 /// Dumps the full parser trace if any test failed.
-pub struct CheckTrace;
+///
+/// The DebugWidth applies to the trace too. Before it was always
+/// DebugWidth::Long, use that to get the parent stacks.
+pub struct CheckTrace(pub DebugWidth);
 
 impl<'s, O, C, E, const TRACK: bool>
     Report<Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>> for CheckTrace
@@ -673,14 +940,17 @@ where
     #[track_caller]
     fn report(&self, test: &Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>) {
         if test.fail.get() {
-            trace(test);
+            trace(test, self.0);
             panic!("test failed")
         }
     }
 }
 
 /// Dumps the full parser trace.
-pub struct Trace;
+///
+/// The DebugWidth applies to the trace too. Before it was always
+/// DebugWidth::Long, use that to get the parent stacks.
+pub struct Trace(pub DebugWidth);
 
 impl<'s, O, C, E, const TRACK: bool>
     Report<Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>> for Trace
@@ -690,12 +960,13 @@ where
     C: Code,
 {
     fn report(&self, test: &Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>) {
-        trace(test);
+        trace(test, self.0);
     }
 }
 
 fn trace<'s, O, C, E, const TRACK: bool>(
     test: &Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>,
+    w: DebugWidth,
 ) where
     O: Debug,
     E: Debug,
@@ -703,19 +974,20 @@ fn trace<'s, O, C, E, const TRACK: bool>(
 {
     struct TracerDebug<'a, 's, C: Code, const TRACK: bool> {
         trace: &'a CTracer<'s, C, TRACK>,
+        w: DebugWidth,
         track_filter: FilterFn<'a, C>,
     }
 
     impl<'a, 's, C: Code, const TRACK: bool> Debug for TracerDebug<'a, 's, C, TRACK> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.trace.write(f, DebugWidth::Long, self.track_filter)
+            self.trace.write(f, self.w, self.track_filter)
         }
     }
 
     println!();
     println!(
         "when parsing '{}' in {} =>",
-        restrict(DebugWidth::Medium, test.span),
+        restrict(w, test.span),
         humantime::format_duration(test.duration)
     );
 
@@ -727,28 +999,97 @@ fn trace<'s, O, C, E, const TRACK: bool>(
         "{:?}",
         TracerDebug {
             trace,
+            w,
             track_filter
         }
     );
 
     match &test.result {
         Ok((rest, token)) => {
-            println!(
-                "rest {}:\"{}\"",
-                rest.location_offset(),
-                restrict(DebugWidth::Medium, *rest)
-            );
+            println!("rest {}:\"{}\"", rest.location_offset(), restrict(w, *rest));
             println!("{:0?}", token);
         }
         Err(e) => {
             println!("error");
-            println!("{:1?}", e);
+            println!("{:1$?}", e, usize::from(w));
+        }
+    }
+}
+
+/// Dumps only the last n tracks of the parser trace.
+pub struct TailTrace(pub usize);
+
+impl<'s, O, C, E, const TRACK: bool>
+    Report<Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>> for TailTrace
+where
+    E: Debug,
+    O: Debug,
+    C: Code,
+{
+    fn report(&self, test: &Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>) {
+        let w = DebugWidth::Medium;
+
+        let mut buf = String::new();
+        if test.x.trace.write_tail(&mut buf, w, self.0).is_err() {
+            return;
+        }
+
+        println!();
+        println!(
+            "when parsing '{}' in {} =>",
+            restrict(w, test.span),
+            humantime::format_duration(test.duration)
+        );
+        print!("{}", buf);
+        match &test.result {
+            Ok((rest, _)) => println!("rest {}:\"{}\"", rest.location_offset(), restrict(w, *rest)),
+            Err(e) => {
+                println!("error");
+                println!("{:1$?}", e, usize::from(w));
+            }
+        }
+    }
+}
+
+/// Dumps the parser trace as markdown list.
+pub struct MarkdownTrace(pub DebugWidth);
+
+impl<'s, O, C, E, const TRACK: bool>
+    Report<Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>> for MarkdownTrace
+where
+    E: Debug,
+    O: Debug,
+    C: Code,
+{
+    fn report(&self, test: &Test<TestTracer<'_, 's, C, TRACK>, Span<'s>, (Span<'s>, O), E>) {
+        let w = self.0;
+
+        let track_filter_r = test.x.trace_filter.borrow();
+        let track_filter = &*track_filter_r;
+
+        let mut buf = String::new();
+        if test
+            .x
+            .trace
+            .write_markdown(&mut buf, w, track_filter)
+            .is_err()
+        {
+            return;
+        }
+
+        println!();
+        println!("Parsing `{}`", restrict(w, test.span));
+        println!();
+        print!("{}", buf);
+        match &test.result {
+            Ok((rest, _)) => println!("- **result** ok, rest `{}`", restrict(w, *rest)),
+            Err(e) => println!("- **result** err {:?}", e),
         }
     }
 }
 
 /// Dumps the full parser trace.
-pub struct RTrace;
+pub struct RTrace(pub DebugWidth);
 
 impl<'s, O, C, E> Report<Test<TestRTracer<'s, C>, Span<'s>, (Span<'s>, O), E>> for RTrace
 where
@@ -757,11 +1098,11 @@ where
     C: Code,
 {
     fn report(&self, test: &Test<TestRTracer<'s, C>, Span<'s>, (Span<'s>, O), E>) {
-        rtrace(test);
+        rtrace(test, self.0);
     }
 }
 
-fn rtrace<'s, O, C, E>(test: &Test<TestRTracer<'s, C>, Span<'s>, (Span<'s>, O), E>)
+fn rtrace<'s, O, C, E>(test: &Test<TestRTracer<'s, C>, Span<'s>, (Span<'s>, O), E>, w: DebugWidth)
 where
     O: Debug,
     E: Debug,
@@ -769,37 +1110,34 @@ where
 {
     struct TracerDebug<'a, 's, C: Code> {
         trace: &'a RTracer<'s, C>,
+        w: DebugWidth,
     }
 
     impl<'a, 's, C: Code> Debug for TracerDebug<'a, 's, C> {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            self.trace.write(f, DebugWidth::Medium)
+            self.trace.write(f, self.w)
         }
     }
 
     println!();
     println!(
         "when parsing '{}' in {} =>",
-        restrict(DebugWidth::Medium, test.span),
+        restrict(w, test.span),
         humantime::format_duration(test.duration)
     );
 
     let trace = &test.x.trace;
 
-    println!("{:?}", TracerDebug { trace });
+    println!("{:?}", TracerDebug { trace, w });
 
     match &test.result {
         Ok((rest, token)) => {
-            println!(
-                "rest {}:\"{}\"",
-                rest.location_offset(),
-                restrict(DebugWidth::Medium, *rest)
-            );
+            println!("rest {}:\"{}\"", rest.location_offset(), restrict(w, *rest));
             println!("{:0?}", token);
         }
         Err(e) => {
             println!("error");
-            println!("{:1?}", e);
+            println!("{:1$?}", e, usize::from(w));
         }
     }
 }
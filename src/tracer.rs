@@ -1,21 +1,65 @@
-use crate::debug::tracer::debug_tracer;
-use crate::error::{DebugWidth, Expect, Hints, ParserError, Suggest};
-use crate::{Code, FilterFn, ParserResult, Span, Tracer};
+use crate::debug::tracer::{debug_tail, debug_tracer, markdown_tracer};
+use crate::error::{DebugWidth, Expect, Hints, ParserError, Suggest, Trivia};
+use crate::{Code, CodeHash, FilterFn, ParserNomResult, ParserResult, Span, Tracer};
+use nom::InputTake;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::{fmt, mem};
 
+/// Callback for CTracer::on_error.
+type ErrorFn<'s, C> = Box<dyn FnMut(&ParserError<'s, C>) + Send + 's>;
+
 /// Tracing and error collection.
 pub struct CTracer<'s, C: Code, const TRACK: bool = true> {
     /// Function call stack.
     pub(crate) func: Vec<C>,
+    /// Span given to enter for each function.
+    pub(crate) func_span: Vec<Span<'s>>,
+    /// Function of the last ok/err. A missing enter pops the frame of the
+    /// parent instead, this is the frame to look at.
+    pub(crate) exited: Option<C>,
 
     /// Collected tracks.
     pub(crate) track: Vec<Track<'s, C>>,
 
     pub(crate) suggest: Vec<SuggestTrack<'s, C>>,
     pub(crate) expect: Vec<ExpectTrack<'s, C>>,
+    /// Skipped trivia per stack frame.
+    pub(crate) trivia: Vec<Vec<Trivia<'s>>>,
+
+    /// Tokens marked as synthetic.
+    pub(crate) synthetic: Vec<(C, Span<'s>)>,
+
+    /// Sequence number for Expect and Suggest.
+    pub(crate) seq: u32,
+
+    /// Maximum offset reached by any enter or step.
+    pub(crate) high_water: usize,
+
+    /// Number of enters per func and offset since the high water mark
+    /// last moved.
+    pub(crate) stall: Vec<(C, usize, usize)>,
+    /// High water mark when stall was last reset.
+    pub(crate) stall_mark: usize,
+    /// Number of enters that count as a stall.
+    pub(crate) stall_limit: usize,
+
+    /// Maximum offset of the rest of any ok.
+    pub(crate) progress: usize,
+    /// Called when the progress advances.
+    pub(crate) progress_fn: Option<Box<dyn FnMut(usize) + Send + 's>>,
+
+    /// Called for every error.
+    pub(crate) error_fn: Option<ErrorFn<'s, C>>,
+
+    /// Keep the suggestions of the toplevel parser if it succeeds.
+    pub(crate) keep_suggest_on_ok: bool,
+
+    /// Skips whitespace, comments etc. for enter_skip_trivia.
+    pub(crate) trivia_fn: Option<Box<dyn Fn(Span<'s>) -> ParserNomResult<'s, C> + Send + 's>>,
 }
 
 impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
@@ -23,19 +67,35 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
     fn new() -> Self {
         Self {
             func: Vec::new(),
+            func_span: Vec::new(),
+            exited: None,
             track: Vec::new(),
             suggest: Vec::new(),
             expect: Vec::new(),
+            trivia: Vec::new(),
+            synthetic: Vec::new(),
+            seq: 0,
+            high_water: 0,
+            stall: Vec::new(),
+            stall_mark: 0,
+            stall_limit: 1000,
+            progress: 0,
+            progress_fn: None,
+            error_fn: None,
+            keep_suggest_on_ok: true,
+            trivia_fn: None,
         }
     }
 
     /// Enter a parser function. Absolutely necessary for the rest.
     fn enter(&mut self, func: C, span: Span<'s>) {
-        self.push_func(func);
+        self.push_func(func, span);
         self.push_suggest(func);
         self.push_expect(func);
+        self.trivia.push(Vec::new());
 
         self.track_enter(span);
+        self.check_stall(func, span);
     }
 
     /// Keep track of steps in a complicated parser.
@@ -48,15 +108,41 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
         self.track_debug(step.into());
     }
 
+    /// Attaches a key/value note to the current parser function.
+    fn note(&mut self, key: &'static str, value: String) {
+        self.track_note(key, value);
+    }
+
+    /// Current parser function.
+    fn current(&self) -> Option<C> {
+        self.func.last().copied()
+    }
+
     /// Adds a suggestion for the current stack frame.
     fn suggest(&mut self, suggest: C, span: Span<'s>) {
         self.add_suggest(suggest, span);
     }
 
+    /// Adds an expectation for the current stack frame.
     fn expect(&mut self, expect: C, span: Span<'s>) {
         self.add_expect(expect, span);
     }
 
+    /// Skipped trivia for the current stack frame. Recorded as step "trivia".
+    fn trivia(&mut self, span: Span<'s>) {
+        self.track_step("trivia", span);
+        self.trivia
+            .last_mut()
+            .expect("Vec<Trivia> is empty")
+            .push(Trivia { span });
+    }
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, code: C, span: Span<'s>) {
+        self.synthetic.push((code, span));
+        self.track_synthetic(code, span);
+    }
+
     /// Keep track of this error.
     fn stash(&mut self, err: ParserError<'s, C>) {
         self.add_expect(err.code, err.span);
@@ -67,6 +153,7 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
         for hint in err.hints.into_iter() {
             match hint {
                 Hints::Nom(_) => {}
+                Hints::Trivia(_) => {}
                 Hints::Suggest(v) => {
                     suggest_vec.push(v);
                 }
@@ -84,19 +171,22 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
         span: Span<'s>,
         val: T,
     ) -> ParserResult<'s, C, (Span<'s>, T)> {
+        self.check_entered();
         self.track_ok(rest, span);
 
         let expect = self.pop_expect();
         self.track_expect(Usage::Drop, Cow::Owned(expect.list));
         let suggest = self.pop_suggest();
         // Keep suggests, sort them out later.
-        // Drop at the toplevel if no error occurs?
+        // At the toplevel it depends on keep_suggest_on_ok.
         if !self.suggest.is_empty() {
             self.append_suggest(suggest.list);
-        } else {
+        } else if self.keep_suggest_on_ok {
             self.suggest.push(suggest);
         }
 
+        self.trivia.pop();
+
         self.track_exit();
         self.pop_func();
 
@@ -105,6 +195,17 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
 
     /// Write a track for an error.
     fn err<'t, T>(&'t mut self, mut err: ParserError<'s, C>) -> ParserResult<'s, C, T> {
+        self.check_entered();
+
+        if let Some(error_fn) = &mut self.error_fn {
+            error_fn(&err);
+        }
+
+        // Keep the innermost call stack.
+        if TRACK && err.stack.is_empty() {
+            err.stack = self.func.clone();
+        }
+
         // Freshly created error needs to be recorded before we overwrite the code.
         if !err.tracing {
             err.tracing = true;
@@ -134,6 +235,10 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
         self.track_suggest(Usage::Use, Cow::Borrowed(&sug.list));
         err.append_suggest(sug.list);
 
+        if let Some(trivia) = self.trivia.pop() {
+            err.append_trivia(trivia);
+        }
+
         self.track_error(&err);
 
         self.track_exit();
@@ -143,6 +248,85 @@ impl<'s, C: Code, const TRACK: bool> Tracer<'s, C> for CTracer<'s, C, TRACK> {
     }
 }
 
+/// Every enter must be finished with ok or err. Checked in debug builds.
+impl<'s, C: Code, const TRACK: bool> Drop for CTracer<'s, C, TRACK> {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && !std::thread::panicking() {
+            assert!(
+                self.func.is_empty(),
+                "CTracer dropped with unfinished parser functions {:?}. missing trace.ok() or trace.err()?",
+                self.func
+            );
+        }
+    }
+}
+
+// capacity
+impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
+    /// Initial capacity of the call stack and the per frame vectors.
+    const STACK_CAPACITY: usize = 64;
+
+    /// New one with preallocated vectors.
+    ///
+    /// The tracks get the given capacity, this is only used when TRACK is true.
+    /// The call stack and the expect/suggest/trivia frames are sized for a
+    /// nesting depth of 64.
+    pub fn with_capacity(tracks: usize) -> Self {
+        let mut trace = Self::new();
+        if TRACK {
+            trace.track = Vec::with_capacity(tracks);
+        }
+        trace.func = Vec::with_capacity(Self::STACK_CAPACITY);
+        trace.func_span = Vec::with_capacity(Self::STACK_CAPACITY);
+        trace.expect = Vec::with_capacity(Self::STACK_CAPACITY);
+        trace.suggest = Vec::with_capacity(Self::STACK_CAPACITY);
+        trace.trivia = Vec::with_capacity(Self::STACK_CAPACITY);
+        trace
+    }
+
+    /// Capacity of the tracks.
+    pub fn track_capacity(&self) -> usize {
+        self.track.capacity()
+    }
+}
+
+// trivia
+impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
+    /// Sets a parser for trivia like whitespace or comments.
+    /// It is applied by enter_skip_trivia.
+    pub fn with_trivia(
+        mut self,
+        f: impl Fn(Span<'s>) -> ParserNomResult<'s, C> + Send + 's,
+    ) -> Self {
+        self.trivia_fn = Some(Box::new(f));
+        self
+    }
+
+    /// Skips the trivia and enters the parser function afterwards.
+    /// The skipped span is recorded as a step "trivia".
+    /// Returns the rest after the trivia.
+    ///
+    /// Without a trivia parser this is the same as enter.
+    pub fn enter_skip_trivia(&mut self, func: C, span: Span<'s>) -> Span<'s> {
+        let (rest, trivia) = match &self.trivia_fn {
+            Some(trivia_fn) => match trivia_fn(span) {
+                Ok((rest, trivia)) => (rest, Some(trivia)),
+                Err(_) => (span, None),
+            },
+            None => (span, None),
+        };
+
+        self.enter(func, rest);
+        if let Some(trivia) = trivia {
+            if !trivia.is_empty() {
+                self.trivia(trivia);
+            }
+        }
+
+        rest
+    }
+}
+
 // output
 impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     /// Write a debug output of the Tracer state.
@@ -152,7 +336,192 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
         w: DebugWidth,
         filter: FilterFn<'_, C>,
     ) -> fmt::Result {
-        debug_tracer(out, w, self, filter)
+        debug_tracer(out, w, TraceStyle::default(), self, filter)
+    }
+
+    /// Write a debug output of the Tracer state with the given style.
+    pub fn write_styled(
+        &self,
+        out: &mut impl fmt::Write,
+        w: DebugWidth,
+        style: TraceStyle,
+        filter: FilterFn<'_, C>,
+    ) -> fmt::Result {
+        debug_tracer(out, w, style, self, filter)
+    }
+
+    /// Write only the last n tracks.
+    pub fn write_tail(&self, out: &mut impl fmt::Write, w: DebugWidth, n: usize) -> fmt::Result {
+        debug_tail(out, w, self, n)
+    }
+
+    /// Write the trace as nested markdown list.
+    pub fn write_markdown(
+        &self,
+        out: &mut impl fmt::Write,
+        w: DebugWidth,
+        filter: FilterFn<'_, C>,
+    ) -> fmt::Result {
+        markdown_tracer(out, w, self, filter)
+    }
+
+    /// All collected tracks. Empty if TRACK is false.
+    pub fn tracks(&self) -> &[Track<'s, C>] {
+        &self.track
+    }
+
+    /// The last n tracks. Usually the interesting ones before an error.
+    pub fn tail_tracks(&self, n: usize) -> &[Track<'s, C>] {
+        &self.track[self.track.len().saturating_sub(n)..]
+    }
+
+    /// Maximum offset reached by any enter or step.
+    /// This is usually a good hint where the real problem is.
+    pub fn high_water(&self) -> usize {
+        self.high_water
+    }
+
+    /// Maximum offset of the rest of any ok.
+    /// This is how far the input has been consumed.
+    pub fn progress(&self) -> usize {
+        self.progress
+    }
+
+    /// Sets a callback that is invoked whenever the progress advances.
+    pub fn on_progress(&mut self, f: impl FnMut(usize) + Send + 's) {
+        self.progress_fn = Some(Box::new(f));
+    }
+
+    /// Counts how often each parser function was entered.
+    /// In order of the first enter. Needs TRACK, otherwise this is empty.
+    pub fn enter_counts(&self) -> Vec<(C, usize)> {
+        let mut counts: Vec<(C, usize)> = Vec::new();
+        for t in &self.track {
+            if let Track::Enter(v) = t {
+                match counts.iter_mut().find(|(func, _)| *func == v.func) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((v.func, 1)),
+                }
+            }
+        }
+        counts
+    }
+
+    /// Counts how often each parser function was entered.
+    /// Same as enter_counts() but as a HashMap. Needs TRACK, otherwise this is empty.
+    pub fn enter_count_map(&self) -> HashMap<C, usize>
+    where
+        C: CodeHash,
+    {
+        let mut counts = HashMap::new();
+        for t in &self.track {
+            if let Track::Enter(v) = t {
+                *counts.entry(v.func).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// All tokens marked with synthetic(). Works without TRACK too.
+    pub fn synthetic_tokens(&self) -> &[(C, Span<'s>)] {
+        &self.synthetic
+    }
+
+    /// The span was marked with synthetic().
+    pub fn is_synthetic(&self, span: Span<'s>) -> bool {
+        self.synthetic
+            .iter()
+            .any(|(_, v)| v.location_offset() == span.location_offset() && v.len() == span.len())
+    }
+
+    /// Sets a callback that is invoked for every error before it is tracked.
+    /// Good place for a breakpoint.
+    pub fn on_error(&mut self, f: impl FnMut(&ParserError<'s, C>) + Send + 's) {
+        self.error_fn = Some(Box::new(f));
+    }
+
+    /// Span from the start of the current parser function up to rest.
+    /// Useful as span for trace.ok() instead of a span_union() of all children.
+    ///
+    /// None if there is no current function or rest doesn't lie within the
+    /// span given to trace.enter().
+    pub fn span_so_far(&self, rest: Span<'s>) -> Option<Span<'s>> {
+        let span = *self.func_span.last()?;
+        let len = rest.location_offset().checked_sub(span.location_offset())?;
+        if span.fragment().get(..len).is_some() {
+            Some(span.take(len))
+        } else {
+            None
+        }
+    }
+
+    /// Number of enters of the same func at the same offset without any
+    /// progress of the high water mark, before a possible stall is reported
+    /// in the trace. The default is 1000.
+    pub fn set_stall_limit(&mut self, limit: usize) {
+        self.stall_limit = limit;
+    }
+
+    /// Keep the suggestions when the toplevel parser succeeds. This is the default.
+    /// Useful for completion, where suggestions are needed regardless of the result.
+    /// With false the suggestions are dropped after a successful parse.
+    pub fn set_keep_suggest_on_ok(&mut self, keep: bool) {
+        self.keep_suggest_on_ok = keep;
+    }
+
+    /// Builds a tree of the parser calls from the tracks.
+    /// Returns the first toplevel call, None if nothing was tracked.
+    pub fn to_tree(&self) -> Option<TraceNode<'s, C>> {
+        let mut stack: Vec<TraceNode<'s, C>> = Vec::new();
+        let mut root = None;
+
+        for t in &self.track {
+            match t {
+                Track::Enter(v) => stack.push(TraceNode {
+                    func: v.func,
+                    span: v.span,
+                    outcome: TraceOutcome::Unfinished,
+                    children: Vec::new(),
+                }),
+                Track::Ok(_) => {
+                    if let Some(node) = stack.last_mut() {
+                        node.outcome = TraceOutcome::Ok;
+                    }
+                }
+                Track::Err(_) => {
+                    if let Some(node) = stack.last_mut() {
+                        node.outcome = TraceOutcome::Err;
+                    }
+                }
+                Track::Exit(_) => {
+                    if let Some(node) = stack.pop() {
+                        match stack.last_mut() {
+                            Some(parent) => parent.children.push(node),
+                            None => {
+                                if root.is_none() {
+                                    root = Some(node);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // unfinished calls
+        while let Some(node) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => {
+                    if root.is_none() {
+                        root = Some(node);
+                    }
+                }
+            }
+        }
+
+        root
     }
 
     pub fn to_results(&mut self) -> (Vec<Expect<'s, C>>, Vec<Suggest<'s, C>>) {
@@ -189,12 +558,26 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     }
 
     fn add_expect(&mut self, code: C, span: Span<'s>) {
-        self.track_expect_single(Usage::Track, code, span);
+        let seq = self.next_seq();
+        self.track_expect_single(Usage::Track, code, span, seq);
         self.expect
             .last_mut()
             .expect("Vec<Expect> is empty")
             .list
-            .push(Expect { code, span })
+            .push(Expect {
+                code,
+                span,
+                related: None,
+                seq,
+            })
+    }
+}
+
+// sequence
+impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
+    fn next_seq(&mut self) -> u32 {
+        self.seq += 1;
+        self.seq
     }
 }
 
@@ -213,11 +596,17 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     }
 
     fn add_suggest(&mut self, code: C, span: Span<'s>) {
+        let seq = self.next_seq();
         self.suggest
             .last_mut()
             .expect("Vec<Suggest> is empty")
             .list
-            .push(Suggest { code, span })
+            .push(Suggest {
+                code,
+                span,
+                related: None,
+                seq,
+            })
     }
 
     fn append_suggest(&mut self, mut suggest: Vec<Suggest<'s, C>>) {
@@ -232,13 +621,58 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
 // call frame tracking
 impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     // enter function
-    fn push_func(&mut self, func: C) {
+    fn push_func(&mut self, func: C, span: Span<'s>) {
         self.func.push(func);
+        self.func_span.push(span);
     }
 
     // leave current function
     fn pop_func(&mut self) {
-        self.func.pop();
+        self.exited = self.func.pop();
+        self.func_span.pop();
+    }
+
+    // ok/err without a matching enter.
+    // The tracer can't tell which parser calls ok/err, so a missing enter
+    // finishes the frame of the parent. This shows when the frames run out,
+    // at the latest at the outermost parser. The last finished frame is the
+    // one that was taken over by the parser without enter.
+    fn check_entered(&self) {
+        debug_assert!(
+            !self.func.is_empty(),
+            "trace.ok() or trace.err() without trace.enter(). forgot to call trace.enter()? last finished {:?}",
+            self.exited
+        );
+    }
+
+    // The same func entered again and again at the same offset usually
+    // is a parser stuck in a loop. This may be a loop over a rule with
+    // children, so the enters are counted per func and offset, until the
+    // high water mark moves. Reported once when the limit is exceeded.
+    fn check_stall(&mut self, func: C, span: Span<'s>) {
+        if self.high_water != self.stall_mark {
+            self.stall.clear();
+            self.stall_mark = self.high_water;
+        }
+
+        let offset = span.location_offset();
+        let count = match self
+            .stall
+            .iter_mut()
+            .find(|(f, o, _)| *f == func && *o == offset)
+        {
+            Some((_, _, n)) => {
+                *n += 1;
+                *n
+            }
+            None => {
+                self.stall.push((func, offset, 1));
+                1
+            }
+        };
+        if count == self.stall_limit + 1 {
+            self.track_debug(format!("possible stall in {} at {}", func, offset));
+        }
     }
 
     // current function
@@ -257,6 +691,7 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
 // basic tracking
 impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     fn track_enter(&mut self, span: Span<'s>) {
+        self.high_water = self.high_water.max(span.location_offset());
         if TRACK {
             let parent = self.parent_vec().clone();
             self.track.push(Track::Enter(EnterTrack {
@@ -268,6 +703,7 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     }
 
     fn track_step(&mut self, step: &'static str, span: Span<'s>) {
+        self.high_water = self.high_water.max(span.location_offset());
         if TRACK {
             let parent = self.parent_vec().clone();
             self.track.push(Track::Step(StepTrack {
@@ -291,6 +727,31 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
         }
     }
 
+    fn track_note(&mut self, key: &'static str, value: String) {
+        if TRACK {
+            let parent = self.parent_vec().clone();
+            self.track.push(Track::Note(NoteTrack {
+                func: self.func(),
+                key,
+                value,
+                parents: parent,
+                _phantom: Default::default(),
+            }));
+        }
+    }
+
+    fn track_synthetic(&mut self, code: C, span: Span<'s>) {
+        if TRACK {
+            let parent = self.parent_vec().clone();
+            self.track.push(Track::Synthetic(SyntheticTrack {
+                func: self.func(),
+                code,
+                span,
+                parents: parent,
+            }));
+        }
+    }
+
     fn track_suggest(&mut self, usage: Usage, suggest: Cow<Vec<Suggest<'s, C>>>) {
         if TRACK {
             if !suggest.is_empty() {
@@ -303,12 +764,17 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
         }
     }
 
-    fn track_expect_single(&mut self, usage: Usage, code: C, span: Span<'s>) {
+    fn track_expect_single(&mut self, usage: Usage, code: C, span: Span<'s>, seq: u32) {
         if TRACK {
             self.track.push(Track::Expect(ExpectTrack {
                 func: self.func(),
                 usage,
-                list: vec![Expect { code, span }],
+                list: vec![Expect {
+                    code,
+                    span,
+                    related: None,
+                    seq,
+                }],
             }));
         }
     }
@@ -326,6 +792,12 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     }
 
     fn track_ok(&mut self, rest: Span<'s>, span: Span<'s>) {
+        if rest.location_offset() > self.progress {
+            self.progress = rest.location_offset();
+            if let Some(progress_fn) = &mut self.progress_fn {
+                progress_fn(self.progress);
+            }
+        }
         if TRACK {
             let parent = self.parent_vec().clone();
             self.track.push(Track::Ok(OkTrack {
@@ -340,10 +812,19 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
     fn track_error(&mut self, err: &ParserError<'s, C>) {
         if TRACK {
             let parent = self.parent_vec().clone();
+            let consumed = match self.func_span.last() {
+                Some(span) => err
+                    .span
+                    .location_offset()
+                    .saturating_sub(span.location_offset()),
+                None => 0,
+            };
             self.track.push(Track::Err(ErrTrack {
                 func: self.func(),
                 span: err.span,
                 err: err.to_string(),
+                backtracked: consumed > 0,
+                consumed,
                 parents: parent,
             }));
         }
@@ -363,8 +844,26 @@ impl<'s, C: Code, const TRACK: bool> CTracer<'s, C, TRACK> {
 
 // Track -----------------------------------------------------------------
 
+/// Output style for CTracer::write_styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceStyle {
+    /// Indentation per level.
+    pub indent: usize,
+    /// ANSI colors for the terminal. Enter is green, err is red.
+    pub color: bool,
+}
+
+impl Default for TraceStyle {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            color: false,
+        }
+    }
+}
+
 /// Hint at how the ExpectTrack and SuggestTrack were used.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Usage {
     /// Newly created, currently in use.
     Track,
@@ -438,6 +937,32 @@ pub struct DebugTrack<'s, C> {
     pub _phantom: PhantomData<Span<'s>>,
 }
 
+/// Track for a key/value note.
+pub struct NoteTrack<'s, C> {
+    /// Function.
+    pub func: C,
+    /// Key.
+    pub key: &'static str,
+    /// Value.
+    pub value: String,
+    /// Parser call stack.
+    pub parents: Vec<C>,
+    /// For the lifetime ...
+    pub _phantom: PhantomData<Span<'s>>,
+}
+
+/// Track for a synthetic token.
+pub struct SyntheticTrack<'s, C> {
+    /// Function.
+    pub func: C,
+    /// Code of the token.
+    pub code: C,
+    /// Span of the recovered region.
+    pub span: Span<'s>,
+    /// Parser call stack.
+    pub parents: Vec<C>,
+}
+
 /// Track for ok results.
 pub struct OkTrack<'s, C> {
     /// Function.
@@ -450,6 +975,16 @@ pub struct OkTrack<'s, C> {
     pub parents: Vec<C>,
 }
 
+impl<'s, C> OkTrack<'s, C> {
+    /// Byte range from the start of the span up to the rest.
+    /// E.g. for a coverage map of the input per parser function.
+    pub fn consumed_range(&self) -> Range<usize> {
+        let start = self.span.location_offset();
+        let end = self.rest.location_offset().max(start);
+        start..end
+    }
+}
+
 /// Track for err results.
 pub struct ErrTrack<'s, C> {
     /// Function.
@@ -458,6 +993,11 @@ pub struct ErrTrack<'s, C> {
     pub span: Span<'s>,
     /// Error message.
     pub err: String, // TODO: check
+    /// The parser consumed some input before it failed.
+    /// These are the spots where a cut might be needed.
+    pub backtracked: bool,
+    /// Bytes between the enter span and the error span.
+    pub consumed: usize,
     /// Parser call stack.
     pub parents: Vec<C>,
 }
@@ -472,12 +1012,38 @@ pub struct ExitTrack<'s, C> {
     pub _phantom: PhantomData<Span<'s>>,
 }
 
+/// Result of a parser call in the TraceNode tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceOutcome {
+    /// Finished with trace.ok().
+    Ok,
+    /// Finished with trace.err().
+    Err,
+    /// Still running when the tree was built.
+    Unfinished,
+}
+
+/// One parser call, built by CTracer::to_tree.
+#[derive(Debug, Clone)]
+pub struct TraceNode<'s, C> {
+    /// Function.
+    pub func: C,
+    /// Span given to enter.
+    pub span: Span<'s>,
+    /// Result of the call.
+    pub outcome: TraceOutcome,
+    /// Called parser functions in order.
+    pub children: Vec<TraceNode<'s, C>>,
+}
+
 /// One track of the parsing trace.
 #[allow(missing_docs)]
 pub enum Track<'s, C: Code> {
     Enter(EnterTrack<'s, C>),
     Step(StepTrack<'s, C>),
     Debug(DebugTrack<'s, C>),
+    Note(NoteTrack<'s, C>),
+    Synthetic(SyntheticTrack<'s, C>),
     Expect(ExpectTrack<'s, C>),
     Suggest(SuggestTrack<'s, C>),
     Ok(OkTrack<'s, C>),
@@ -492,6 +1058,8 @@ impl<'s, C: Code> Track<'s, C> {
             Track::Enter(v) => v.func,
             Track::Step(v) => v.func,
             Track::Debug(v) => v.func,
+            Track::Note(v) => v.func,
+            Track::Synthetic(v) => v.func,
             Track::Expect(v) => v.func,
             Track::Suggest(v) => v.func,
             Track::Ok(v) => v.func,
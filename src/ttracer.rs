@@ -0,0 +1,124 @@
+use crate::debug::restrict;
+use crate::error::{DebugWidth, Expect, ParserError, Suggest};
+use crate::etracer::ErrorTracer;
+use crate::{Code, ParserResult, Span, Tracer};
+use tracing::span::EnteredSpan;
+
+/// Records the parser frames as spans of the `tracing` crate.
+///
+/// Each enter opens a span at TRACE level, ok/err close it again. The
+/// span name is fixed, the Display of the func code is recorded in the
+/// field `func`. Expect and suggest are collected like ErrorTracer.
+///
+/// Needs the feature `tracing`.
+pub struct TracingTracer<'s, C: Code> {
+    errors: ErrorTracer<'s, C>,
+    spans: Vec<EnteredSpan>,
+}
+
+impl<'s, C: Code> Tracer<'s, C> for TracingTracer<'s, C> {
+    /// New one.
+    fn new() -> Self {
+        Self {
+            errors: ErrorTracer::new(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Enter a parser function. Opens a new span.
+    fn enter(&mut self, func: C, span: Span<'s>) {
+        self.errors.enter(func, span);
+
+        let span = tracing::trace_span!(
+            "parse",
+            func = %func,
+            offset = span.location_offset(),
+            input = %restrict(DebugWidth::Medium, span)
+        );
+        self.spans.push(span.entered());
+    }
+
+    /// Emits an event for the step.
+    fn step(&mut self, step: &'static str, span: Span<'s>) {
+        tracing::trace!(
+            step,
+            offset = span.location_offset(),
+            input = %restrict(DebugWidth::Medium, span)
+        );
+    }
+
+    /// Emits an event for the debug message.
+    fn debug<T: Into<String>>(&mut self, step: T) {
+        let step = step.into();
+        tracing::trace!(debug = %step);
+    }
+
+    /// Emits an event for the note.
+    fn note(&mut self, key: &'static str, value: String) {
+        tracing::trace!(note = key, value = %value);
+    }
+
+    /// Current parser function.
+    fn current(&self) -> Option<C> {
+        self.errors.current()
+    }
+
+    /// Adds a suggestion for the current stack frame.
+    fn suggest(&mut self, suggest: C, span: Span<'s>) {
+        self.errors.suggest(suggest, span);
+    }
+
+    /// Adds an expect for the current stack frame.
+    fn expect(&mut self, expect: C, span: Span<'s>) {
+        self.errors.expect(expect, span);
+    }
+
+    /// Skipped trivia for the current stack frame.
+    fn trivia(&mut self, span: Span<'s>) {
+        self.errors.trivia(span);
+    }
+
+    /// Marks a token that was fabricated during error recovery.
+    fn synthetic(&mut self, code: C, span: Span<'s>) {
+        tracing::trace!(synthetic = %code, offset = span.location_offset());
+    }
+
+    /// Keep track of this error.
+    fn stash(&mut self, err: ParserError<'s, C>) {
+        tracing::trace!(stash = %err.code, offset = err.span.location_offset());
+        self.errors.stash(err);
+    }
+
+    /// Closes the span for an ok result.
+    fn ok<'t, T>(
+        &'t mut self,
+        rest: Span<'s>,
+        span: Span<'s>,
+        val: T,
+    ) -> ParserResult<'s, C, (Span<'s>, T)> {
+        tracing::trace!(
+            ok = %restrict(DebugWidth::Medium, span),
+            rest = rest.location_offset()
+        );
+        self.spans.pop();
+        self.errors.ok(rest, span, val)
+    }
+
+    /// Closes the span for an error.
+    fn err<'t, T>(&'t mut self, err: ParserError<'s, C>) -> ParserResult<'s, C, T> {
+        tracing::trace!(
+            err = %err.code,
+            offset = err.span.location_offset()
+        );
+        self.spans.pop();
+        self.errors.err(err)
+    }
+}
+
+// output
+impl<'s, C: Code> TracingTracer<'s, C> {
+    /// Remaining expect and suggest values.
+    pub fn to_results(&mut self) -> (Vec<Expect<'s, C>>, Vec<Suggest<'s, C>>) {
+        self.errors.to_results()
+    }
+}
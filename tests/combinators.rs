@@ -0,0 +1,557 @@
+mod common;
+
+use common::XCode;
+use iparse::combinators::{
+    alt_parse, cut_parse, delimited_parse, fold_many0_parse, map_parse, parse_all, parse_checked,
+    peek_parse, span_parser, verify_parse,
+};
+use iparse::error::{into_nom, DebugWidth, ParserError};
+use iparse::test::{test_parse, CheckTrace, Custom, Test};
+use iparse::tracer::{CTracer, Track};
+use iparse::{
+    parse_tests, IntoParserResultAddCode, ParseAsOptional, Parser, ParserNomResult, ParserResult,
+    Span, Tracer, TrackParseResult,
+};
+use nom::bytes::complete::tag;
+use nom::character::complete::{char as nchar, digit1};
+use nom::combinator::cut;
+use nom::sequence::terminated;
+use nom::InputTake;
+use std::cell::Cell;
+use std::rc::Rc;
+
+struct ParseNumber;
+
+impl<'s> Parser<'s, u32, XCode> for ParseNumber {
+    fn id() -> XCode {
+        XCode::Number
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, u32)> {
+        trace.enter(Self::id(), rest);
+        let (rest, tok) = digit1::<_, nom::error::Error<_>>(rest).track_as(trace, Self::id())?;
+        trace.ok(rest, tok, tok.parse::<u32>().unwrap())
+    }
+}
+
+#[test]
+pub fn test_parse_all() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("1;x;22;;333");
+    trace.enter(XCode::All, span);
+    let (items, errors) = parse_all(&mut trace, span, ParseNumber::parse, ';');
+    let (rest, _) = span.take_split(span.len());
+    let _ = trace.ok(rest, span, ());
+
+    assert_eq!(items, vec![1, 22, 333]);
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].code, XCode::Number);
+    assert_eq!(errors[0].span.location_offset(), 2);
+    assert_eq!(errors[1].span.location_offset(), 7);
+
+    // Returned only, not stashed with the tracer.
+    assert!(!trace.tracks().iter().any(|v| match v {
+        Track::Expect(v) => v.func == XCode::All && !v.list.is_empty(),
+        _ => false,
+    }));
+}
+
+#[test]
+pub fn test_map_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, v) = map_parse(ParseNumber::parse(&mut trace, Span::new("12;")), |v| v * 2).unwrap();
+    assert_eq!(v, 24);
+    assert_eq!(*rest.fragment(), ";");
+
+    // errors pass through untouched
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseNumber::parse(&mut trace, Span::new("x")).unwrap_err();
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err2 = map_parse(ParseNumber::parse(&mut trace, Span::new("x")), |v| v * 2).unwrap_err();
+    assert_eq!(err, err2);
+    assert_eq!(err2.code, XCode::Number);
+}
+
+#[test]
+pub fn test_verify_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("12;");
+    let (rest, v) = verify_parse(
+        span,
+        ParseNumber::parse(&mut trace, span),
+        XCode::All,
+        |v| *v < 100,
+    )
+    .unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(*rest.fragment(), ";");
+
+    // the error is at the rejected token
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("x;123;");
+    let (rest, _) = span.take_split(2);
+    let err = verify_parse(
+        rest,
+        ParseNumber::parse(&mut trace, rest),
+        XCode::All,
+        |v| *v < 100,
+    )
+    .unwrap_err();
+    assert_eq!(err.code, XCode::All);
+    assert_eq!(err.span.location_offset(), 2);
+    assert_eq!(*err.span.fragment(), "123");
+
+    // errors of the parser pass through
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("x");
+    let err = verify_parse(
+        span,
+        ParseNumber::parse(&mut trace, span),
+        XCode::All,
+        |_| true,
+    )
+    .unwrap_err();
+    assert_eq!(err.code, XCode::Number);
+}
+
+#[test]
+pub fn test_peek_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("12;");
+    let (rest, v) = peek_parse(&mut trace, span, ParseNumber::parse).unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(rest, span);
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("x;");
+    let err = peek_parse(&mut trace, span, ParseNumber::parse).unwrap_err();
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    assert_eq!(err, ParseNumber::parse(&mut trace, span).unwrap_err());
+}
+
+#[test]
+pub fn test_into_nom() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let parse_number = |i| into_nom(ParseNumber::parse(&mut trace, i));
+    let mut parse_stmt = terminated(parse_number, nchar(';'));
+
+    let (rest, v) = parse_stmt(Span::new("12;x")).unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(*rest.fragment(), "x");
+    assert!(matches!(
+        parse_stmt(Span::new("x")),
+        Err(nom::Err::Error(_))
+    ));
+}
+
+#[test]
+pub fn test_failure_roundtrip() {
+    let span = Span::new("x");
+
+    let err: ParserError<'_, XCode> =
+        nom::Err::Failure(ParserError::new(XCode::Number, span)).into();
+    assert!(err.is_failure());
+    assert!(matches!(
+        into_nom::<_, ()>(Err(err)),
+        Err(nom::Err::Failure(_))
+    ));
+
+    let err: ParserError<'_, XCode> = nom::Err::Error(ParserError::new(XCode::Number, span)).into();
+    assert!(!err.is_failure());
+
+    let err: ParserError<'_, XCode> =
+        nom::Err::Failure(nom::error::Error::new(span, nom::error::ErrorKind::Digit)).into();
+    assert!(err.is_failure());
+
+    // optional() keeps a cut() a Failure.
+    let r: ParserNomResult<'_, XCode> = cut(digit1)(span);
+    let err = r.optional().unwrap_err();
+    assert!(err.is_failure());
+    assert!(matches!(
+        into_nom::<_, ()>(Err(err)),
+        Err(nom::Err::Failure(_))
+    ));
+
+    let r: ParserNomResult<'_, XCode> = cut(digit1)(span);
+    let err = r.optional_with(&|_| {}).unwrap_err();
+    assert!(err.is_failure());
+
+    let r: ParserNomResult<'_, XCode> = cut(digit1)(span);
+    let err = r.optional_or(span).unwrap_err();
+    assert!(err.is_failure());
+}
+
+struct ParseKeyword;
+
+impl<'s> Parser<'s, Span<'s>, XCode> for ParseKeyword {
+    fn id() -> XCode {
+        XCode::All
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, Span<'s>)> {
+        trace.enter(Self::id(), rest);
+        let (rest, tok) = span_parser(trace, rest, XCode::Number, digit1).track(trace)?;
+        trace.ok(rest, tok, tok)
+    }
+}
+
+#[test]
+pub fn test_span_parser() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, tok) = ParseKeyword::parse(&mut trace, Span::new("12x")).unwrap();
+    assert_eq!(*tok.fragment(), "12");
+    assert_eq!(*rest.fragment(), "x");
+
+    let err = ParseKeyword::parse(&mut trace, Span::new("x")).unwrap_err();
+    assert_eq!(err.code, XCode::Number);
+}
+
+#[test]
+pub fn test_incomplete() {
+    let streaming_tag = |i| nom::bytes::streaming::tag::<_, _, ParserError<'_, XCode>>("abc")(i);
+
+    let res = streaming_tag(Span::new("ab"));
+    assert!(matches!(res, Err(nom::Err::Incomplete(_))));
+    let err: ParserError<'_, XCode> = res.unwrap_err().into();
+    assert!(err.is_incomplete());
+
+    let err = streaming_tag(Span::new("ab")).optional().unwrap_err();
+    assert_eq!(err.code, XCode::Incomplete);
+
+    let err = streaming_tag(Span::new("ab"))
+        .into_with_code(XCode::Number)
+        .unwrap_err();
+    assert_eq!(err.code, XCode::Number);
+    assert!(err.is_expected(XCode::Incomplete));
+}
+
+fn cmp_number(v: &u32, test: u32) -> Result<(), String> {
+    if *v == test {
+        Ok(())
+    } else {
+        Err(format!("number {} but expected {}", v, test))
+    }
+}
+
+#[test]
+pub fn test_ok_msg() {
+    test_parse("12", ParseNumber::parse)
+        .ok_msg(cmp_number, 12)
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+#[should_panic(expected = "test failed")]
+pub fn test_ok_msg_fail() {
+    test_parse("12", ParseNumber::parse)
+        .ok_msg(cmp_number, 13)
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+pub fn test_parse_tests() {
+    parse_tests!(ParseNumber::parse, &CheckTrace(DebugWidth::Short);
+        ("12", ""),
+        ("12;", ";", ok_msg(cmp_number, 12), okok()),
+        ("7 x", " x", ok_msg(cmp_number, 7)),
+    );
+}
+
+#[test]
+#[should_panic(expected = "test failed")]
+pub fn test_parse_tests_fail() {
+    parse_tests!(ParseNumber::parse, &CheckTrace(DebugWidth::Short);
+        ("12;", ""),
+    );
+}
+
+#[test]
+pub fn test_cut_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = cut_parse(ParseNumber::parse(&mut trace, Span::new("x"))).unwrap_err();
+    assert!(err.is_failure());
+    assert_eq!(err.code, XCode::Number);
+    assert!(matches!(
+        into_nom::<_, ()>(Err(err)),
+        Err(nom::Err::Failure(_))
+    ));
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (_, v) = cut_parse(ParseNumber::parse(&mut trace, Span::new("12"))).unwrap();
+    assert_eq!(v, 12);
+}
+
+fn parse_semi<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, Span<'s>)> {
+    trace.enter(XCode::All, rest);
+    let (rest, tok) = tag::<_, _, ParserError<'s, XCode>>(";")(rest).track_as(trace, XCode::All)?;
+    trace.ok(rest, tok, tok)
+}
+
+#[test]
+pub fn test_then_parse() {
+    test_parse("12;7", ParseNumber::parse)
+        .then_parse(parse_semi)
+        .then_parse(ParseNumber::parse)
+        .ok_msg(cmp_number, 7)
+        .rest("")
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+#[should_panic(expected = "then_parse")]
+pub fn test_then_parse_fail() {
+    let _ = test_parse("x;7", ParseNumber::parse).then_parse(parse_semi);
+}
+
+#[test]
+#[should_panic]
+pub fn test_then_parse_check_fail() {
+    test_parse("12;7", ParseNumber::parse)
+        .ok_msg(cmp_number, 13)
+        .then_parse(parse_semi)
+        .rest("7")
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+fn parse_counted<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, u32)> {
+    thread_local! {
+        static CALLS: Cell<u32> = const { Cell::new(0) };
+    }
+    let n = CALLS.with(|v| {
+        v.set(v.get() + 1);
+        v.get()
+    });
+    trace.enter(XCode::Number, rest);
+    if n % 2 == 1 {
+        trace.ok(rest, rest, n)
+    } else {
+        trace.err(ParserError::new(XCode::Number, rest))
+    }
+}
+
+#[test]
+pub fn test_assert_idempotent() {
+    test_parse("12;", ParseNumber::parse)
+        .assert_idempotent(ParseNumber::parse)
+        .q(&CheckTrace(DebugWidth::Short));
+    test_parse("x", ParseNumber::parse)
+        .assert_idempotent(ParseNumber::parse)
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+#[should_panic(expected = "test failed")]
+pub fn test_assert_idempotent_fail() {
+    test_parse("12", parse_counted)
+        .assert_idempotent(parse_counted)
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+pub fn test_custom_report() {
+    let elapsed = Rc::new(Cell::new(None));
+    let elapsed2 = elapsed.clone();
+
+    test_parse("12", ParseNumber::parse).q(&Custom(Box::new(move |t: &Test<_, _, _, _>| {
+        elapsed2.set(Some(t.elapsed()));
+    })));
+
+    assert!(elapsed.get().is_some());
+}
+
+fn eq_number(v: &u32, test: u32) -> bool {
+    *v == test
+}
+
+#[test]
+pub fn test_check_ok() {
+    let t = test_parse("12;", ParseNumber::parse);
+    assert!(t.check_ok(eq_number, 12));
+    assert!(!t.check_ok(eq_number, 13));
+    assert!(t.check_rest(";"));
+    assert!(!t.check_rest(""));
+    assert!(!t.fail.get());
+
+    let t = test_parse("x", ParseNumber::parse);
+    assert!(!t.check_ok(eq_number, 12));
+    assert!(!t.check_rest("x"));
+    assert!(!t.fail.get());
+}
+
+struct ParseParens;
+
+impl<'s> Parser<'s, u32, XCode> for ParseParens {
+    fn id() -> XCode {
+        XCode::All
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, u32)> {
+        trace.enter(Self::id(), rest);
+        let (rest, (span, v)) = delimited_parse(
+            trace,
+            rest,
+            XCode::All,
+            tag("("),
+            ParseNumber::parse,
+            tag(")"),
+        )
+        .track(trace)?;
+        trace.ok(rest, span, v)
+    }
+}
+
+#[test]
+pub fn test_delimited_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, v) = ParseParens::parse(&mut trace, Span::new("(12);")).unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(*rest.fragment(), ";");
+    assert!(trace.tracks().iter().any(|v| match v {
+        Track::Ok(v) => v.func == XCode::All && *v.span.fragment() == "(12)",
+        _ => false,
+    }));
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseParens::parse(&mut trace, Span::new("(12;")).unwrap_err();
+    assert_eq!(err.code, XCode::All);
+    assert_eq!(err.span.location_offset(), 3);
+    assert_eq!(err.suggest_as_ref()[0].code, XCode::All);
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseParens::parse(&mut trace, Span::new("12)")).unwrap_err();
+    assert_eq!(err.code, XCode::Nom);
+}
+
+struct ParseValue;
+
+impl<'s> Parser<'s, u32, XCode> for ParseValue {
+    fn id() -> XCode {
+        XCode::All
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, u32)> {
+        trace.enter(Self::id(), rest);
+        let (rest, v) = alt_parse(
+            trace,
+            rest,
+            XCode::All,
+            &[
+                (XCode::All, ParseParens::parse),
+                (XCode::Number, ParseNumber::parse),
+            ],
+        )
+        .track(trace)?;
+        trace.ok(rest, rest, v)
+    }
+}
+
+#[test]
+pub fn test_alt_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, v) = ParseValue::parse(&mut trace, Span::new("12;")).unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(*rest.fragment(), ";");
+    assert!(trace.tracks().iter().any(|v| match v {
+        Track::Debug(v) => v.dbg == "alt -> Number",
+        _ => false,
+    }));
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseValue::parse(&mut trace, Span::new(";")).unwrap_err();
+    assert_eq!(err.code, XCode::All);
+    assert_eq!(err.span.location_offset(), 0);
+}
+
+fn parse_sum<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, (Span<'s>, u32))> {
+    trace.enter(XCode::All, rest);
+    let (rest, (span, sum)) = fold_many0_parse(
+        trace,
+        rest,
+        |trace, rest| {
+            let (rest, v) = ParseNumber::parse(trace, rest)?;
+            let (rest, _) = nchar::<_, ParserError<'_, XCode>>(';')(rest)?;
+            Ok((rest, v))
+        },
+        0,
+        |acc, v| acc + v,
+    )
+    .track(trace)?;
+    trace.ok(rest, span, (span, sum))
+}
+
+fn parse_nothing<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, (Span<'s>, u32))> {
+    trace.enter(XCode::All, rest);
+    let (rest, v) =
+        fold_many0_parse(trace, rest, |_, rest| Ok((rest, 1)), 0, |acc, v| acc + v).track(trace)?;
+    trace.ok(rest, rest, v)
+}
+
+#[test]
+pub fn test_fold_many0_parse() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, (span, sum)) = parse_sum(&mut trace, Span::new("1;22;3;x")).unwrap();
+    assert_eq!(sum, 26);
+    assert_eq!(*span.fragment(), "1;22;3;");
+    assert_eq!(*rest.fragment(), "x");
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, (span, sum)) = parse_sum(&mut trace, Span::new("x")).unwrap();
+    assert_eq!(sum, 0);
+    assert!(span.is_empty());
+    assert_eq!(*rest.fragment(), "x");
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = parse_nothing(&mut trace, Span::new("x")).unwrap_err();
+    assert_eq!(err.code, XCode::Nom);
+}
+
+fn parse_panic<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, u32)> {
+    trace.enter(XCode::All, rest);
+    let _ = ParseNumber::parse(trace, rest).track(trace)?;
+    panic!("boom");
+}
+
+#[test]
+pub fn test_parse_checked() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, v) = parse_checked(&mut trace, Span::new("12;"), ParseNumber::parse).unwrap();
+    assert_eq!(v, 12);
+    assert_eq!(*rest.fragment(), ";");
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = parse_checked(&mut trace, Span::new("12;"), parse_panic).unwrap_err();
+    assert_eq!(err.code, XCode::Nom);
+    assert!(err.is_failure());
+    assert!(trace.current().is_none());
+    assert!(trace.tracks().iter().any(|v| match v {
+        Track::Debug(v) => v.dbg == "panic: boom",
+        _ => false,
+    }));
+}
@@ -0,0 +1,49 @@
+//! Code shared by the integration tests.
+
+// Not every test uses every variant.
+#![allow(dead_code)]
+
+use iparse::Code;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum XCode {
+    Nom,
+    Incomplete,
+    All,
+    Number,
+    Keyword,
+    Char,
+    Pair,
+    Statement,
+    Section,
+    Outer,
+    Inner,
+    Semicolon,
+}
+
+impl Code for XCode {
+    const NOM_ERROR: Self = Self::Nom;
+    const NOM_FAILURE: Self = Self::Nom;
+    const PARSE_INCOMPLETE: Self = Self::Incomplete;
+
+    fn short_name(&self) -> &'static str {
+        match self {
+            XCode::Pair => "P",
+            _ => "",
+        }
+    }
+
+    fn literal(&self) -> Option<&'static str> {
+        match self {
+            XCode::Semicolon => Some(";"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for XCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,632 @@
+use iparse::error::{sort_diagnostics, CodeError, Expect, Hints, ParserError};
+use iparse::etracer::ErrorTracer;
+use iparse::rtracer::RTracer;
+use iparse::tracer::CTracer;
+use iparse::{
+    Code, ParseAsOptional, ParseAsRequired, Parser, ParserResult, ResultExt, Span, Tracer,
+    TrackParseResult,
+};
+use nom::InputTake;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+// Own XCode, PARSE_INCOMPLETE maps to Nom here.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum XCode {
+    Nom,
+    Level1,
+    Level2,
+    Level3,
+}
+
+impl Code for XCode {
+    const NOM_ERROR: Self = Self::Nom;
+    const NOM_FAILURE: Self = Self::Nom;
+    const PARSE_INCOMPLETE: Self = Self::Nom;
+}
+
+impl Display for XCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+struct ParseLevel1;
+
+impl<'s> Parser<'s, (), XCode> for ParseLevel1 {
+    fn id() -> XCode {
+        XCode::Level1
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.err(ParserError::new(Self::id(), rest))
+    }
+}
+
+struct ParseLevel2;
+
+impl<'s> Parser<'s, (), XCode> for ParseLevel2 {
+    fn id() -> XCode {
+        XCode::Level2
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.expect(Self::id(), rest);
+        let (rest, _) = ParseLevel1::parse(trace, rest).track_as(trace, Self::id())?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+struct ParseLevel3;
+
+impl<'s> Parser<'s, (), XCode> for ParseLevel3 {
+    fn id() -> XCode {
+        XCode::Level3
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.expect(Self::id(), rest);
+        let (rest, _) = ParseLevel2::parse(trace, rest).track_as(trace, Self::id())?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+#[test]
+pub fn test_into_code_chain() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseLevel3::parse(&mut trace, Span::new("x")).unwrap_err();
+
+    let codes: Vec<_> = err.expect_as_ref().iter().map(|v| v.code).collect();
+    assert_eq!(err.code, XCode::Level3);
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2, XCode::Level3]);
+}
+
+fn codes_via<'s, T: Tracer<'s, XCode>>(mut trace: T, txt: &'s str) -> Vec<XCode> {
+    let err = ParseLevel3::parse(&mut trace, Span::new(txt)).unwrap_err();
+    err.expect_as_ref().iter().map(|v| v.code).collect()
+}
+
+#[test]
+pub fn test_explicit_expect() {
+    let expected = vec![XCode::Level1, XCode::Level2, XCode::Level3];
+    assert_eq!(codes_via(CTracer::<'_, XCode>::new(), "x"), expected);
+    assert_eq!(codes_via(RTracer::<'_, XCode>::new(), "x"), expected);
+    assert_eq!(codes_via(ErrorTracer::<'_, XCode>::new(), "x"), expected);
+}
+
+#[test]
+pub fn test_is_expected2() {
+    let span = Span::new("x");
+
+    // 1 entry
+    let mut err = ParserError::new(XCode::Level2, span);
+    err.add_expect(XCode::Level1, span);
+    assert!(err.is_expected2(XCode::Level1, XCode::Level2));
+    assert!(!err.is_expected2(XCode::Level2, XCode::Level1));
+    assert!(!err.is_expected2(XCode::Level1, XCode::Level3));
+
+    // 2 entries, parent is on the list too
+    let mut err = ParserError::new(XCode::Level2, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level2, span);
+    assert!(err.is_expected2(XCode::Level1, XCode::Level2));
+    assert!(!err.is_expected2(XCode::Level2, XCode::Level1));
+
+    // 2 entries, code is not the parent
+    let mut err = ParserError::new(XCode::Level3, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level2, span);
+    assert!(err.is_expected2(XCode::Level1, XCode::Level2));
+    assert!(err.is_expected2(XCode::Level2, XCode::Level3));
+    assert!(!err.is_expected2(XCode::Level1, XCode::Level3));
+
+    // 3 entries
+    let mut err = ParserError::new(XCode::Level3, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level2, span);
+    err.add_expect(XCode::Level3, span);
+    assert!(err.is_expected2(XCode::Level1, XCode::Level2));
+    assert!(err.is_expected2(XCode::Level2, XCode::Level3));
+    assert!(!err.is_expected2(XCode::Level1, XCode::Level3));
+    assert!(!err.is_expected2(XCode::Level3, XCode::Level2));
+}
+
+#[test]
+pub fn test_cloned() {
+    let span = Span::new("x");
+
+    let mut err = ParserError::new(XCode::Level2, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_suggest(XCode::Level3, span);
+
+    let err_ref = &err;
+    let exp = err_ref.cloned_expect();
+    let sug = err_ref.cloned_suggest();
+    assert_eq!(exp.len(), 1);
+    assert_eq!(exp[0].code, XCode::Level1);
+    assert_eq!(sug.len(), 1);
+    assert_eq!(sug[0].code, XCode::Level3);
+
+    // nothing was drained
+    assert_eq!(err.expect_as_ref().len(), 1);
+    assert_eq!(err.suggest_as_ref().len(), 1);
+}
+
+#[test]
+pub fn test_suggestions_at() {
+    let span = Span::new("let abc");
+    let (word, _) = span.take_split(4);
+
+    let mut err = ParserError::new(XCode::Level1, span);
+    err.add_suggest(XCode::Level2, word);
+    err.add_suggest(XCode::Level3, span);
+
+    let at = |o| -> Vec<_> { err.suggestions_at(o).iter().map(|v| v.code).collect() };
+    assert_eq!(at(0), vec![XCode::Level3]);
+    assert_eq!(at(4), vec![XCode::Level2, XCode::Level3]);
+    assert_eq!(at(7), vec![XCode::Level2, XCode::Level3]);
+    assert_eq!(at(8), Vec::<XCode>::new());
+}
+
+#[test]
+pub fn test_display_hints() {
+    let span = Span::new("ä\"b");
+
+    let mut err = ParserError::new(XCode::Level2, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_suggest(XCode::Level3, span);
+
+    assert_eq!(format!("{}", err.expect_as_ref()[0]), "Level1 \"ä\"b\"");
+    assert_eq!(format!("{}", err.suggest_as_ref()[0]), "Level3 \"ä\"b\"");
+    assert_eq!(
+        format!("{:?}", err.expect_as_ref()[0]),
+        "Level1:\"\\u{e4}\\\"b\""
+    );
+}
+
+#[test]
+pub fn test_sorted() {
+    let span = Span::new("ab\ncd");
+    let (second, first) = span.take_split(3);
+
+    let mut err = ParserError::new(XCode::Level3, span);
+    err.add_expect(XCode::Level2, second);
+    err.add_expect(XCode::Level1, first);
+    err.add_suggest(XCode::Level2, second);
+    err.add_suggest(XCode::Level1, first);
+
+    let codes: Vec<_> = err.expect_sorted().iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2]);
+    let codes: Vec<_> = err.suggest_sorted().iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2]);
+
+    // synthetic spans only have an offset and a line.
+    let second = unsafe { ParserError::at(XCode::Level2, 20, 3) }.span;
+    let first = unsafe { ParserError::at(XCode::Level1, 10, 2) }.span;
+    let mut err = unsafe { ParserError::at(XCode::Level3, 0, 1) };
+    err.add_expect(XCode::Level2, second);
+    err.add_expect(XCode::Level1, first);
+    err.add_suggest(XCode::Level2, second);
+    err.add_suggest(XCode::Level1, first);
+
+    let codes: Vec<_> = err.expect_sorted().iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2]);
+    let codes: Vec<_> = err.suggest_sorted().iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2]);
+}
+
+#[test]
+pub fn test_code_error() {
+    let span = Span::new("ab\ncd");
+    let (second, _) = span.take_split(3);
+
+    let err = ParserError::new(XCode::Level1, second);
+    assert_eq!(err.code_only(), (XCode::Level1, 3, 2));
+
+    let cerr: CodeError<XCode> = err.into();
+    assert_eq!(cerr.offset, 3);
+    assert_eq!(cerr.line, 2);
+    assert_eq!(cerr.to_string(), "Level1 at line 2 offset 3");
+}
+
+#[test]
+pub fn test_eq() {
+    let span = Span::new("ab");
+    let span2 = Span::new("ab");
+
+    let mut err = ParserError::new(XCode::Level2, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level3, span);
+
+    let mut other = ParserError::new(XCode::Level2, span2);
+    other.add_expect(XCode::Level3, span2);
+    other.add_expect(XCode::Level1, span2);
+    assert_eq!(err, other);
+
+    other.add_suggest(XCode::Level1, span2);
+    assert_ne!(err, other);
+
+    let (rest, _) = span.take_split(1);
+    assert_ne!(
+        ParserError::new(XCode::Level2, span),
+        ParserError::new(XCode::Level2, rest)
+    );
+
+    let mut failure = ParserError::new(XCode::Level2, span);
+    failure.failure = true;
+    assert_ne!(ParserError::new(XCode::Level2, span), failure);
+
+    // The stack is ignored.
+    let mut stacked = ParserError::new(XCode::Level2, span);
+    stacked.stack = vec![XCode::Level1, XCode::Level2];
+    assert_eq!(ParserError::new(XCode::Level2, span), stacked);
+}
+
+#[test]
+pub fn test_grouped_dedup() {
+    let span = Span::new("ab");
+    let (rest, _) = span.take_split(1);
+
+    let mut err = ParserError::new(XCode::Level3, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level1, span);
+    err.add_expect(XCode::Level2, span);
+    err.add_expect(XCode::Level1, rest);
+
+    let grp = err.expect_grouped_by_offset();
+    assert_eq!(grp.len(), 2);
+    assert_eq!(grp[0].0, 1);
+    assert_eq!(grp[0].1.len(), 1);
+    assert_eq!(grp[1].0, 0);
+    let codes: Vec<_> = grp[1].1.iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level2, XCode::Level1]);
+
+    let txt = format!("{:1?}", err);
+    assert_eq!(txt.matches("Level1").count(), 2);
+}
+
+#[test]
+pub fn test_add_all() {
+    let span = Span::new("ab");
+    let (rest, _) = span.take_split(1);
+
+    let mut err = ParserError::new(XCode::Level3, span);
+    err.add_expect_all(&[(XCode::Level1, span), (XCode::Level2, rest)]);
+    err.add_suggest_all(&[(XCode::Level2, rest)]);
+
+    let codes: Vec<_> = err.expect_as_ref().iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level1, XCode::Level2]);
+    assert_eq!(err.suggest_as_ref()[0].span.location_offset(), 1);
+}
+
+#[test]
+pub fn test_summary() {
+    let span = Span::new("ab\ncäd");
+    let (rest, _) = span.take_split(6);
+
+    let err = ParserError::new(XCode::Level1, rest);
+    assert_eq!(err.summary(), "Level1 expected at 2:3");
+}
+
+#[test]
+pub fn test_related() {
+    let span = Span::new("\"abc");
+    let (rest, _) = span.take_split(4);
+
+    let mut err = ParserError::new(XCode::Level1, rest);
+    err.add_expect(XCode::Level2, rest);
+    err.add_expect_related(XCode::Level3, rest, span.take(1));
+    err.add_suggest_related(XCode::Level3, rest, span.take(1));
+
+    let expect = err.expect_as_ref();
+    assert_eq!(expect[0].related, None);
+    assert_eq!(expect[1].related.map(|v| v.location_offset()), Some(0));
+    assert_eq!(err.suggest_as_ref()[0].related, Some(span.take(1)));
+
+    let dbg = format!("{:2?}", err);
+    assert!(dbg.contains("Level3:4:\"\" related to 0"));
+}
+
+#[test]
+pub fn test_from_code() {
+    let err = ParserError::from_code(XCode::Level1);
+    assert_eq!(err.code, XCode::Level1);
+    assert!(err.span.is_empty());
+    assert_eq!(err.span.location_offset(), 0);
+
+    // Safety: only offset and line are used.
+    let err = unsafe { ParserError::at(XCode::Level2, 17, 3) };
+    assert_eq!(err.code, XCode::Level2);
+    assert!(err.span.is_empty());
+    assert_eq!(err.span.location_offset(), 17);
+    assert_eq!(err.span.location_line(), 3);
+    assert_eq!(CodeError::from(&err).line, 3);
+}
+
+#[test]
+pub fn test_or_code() {
+    let span = Span::new("abc");
+
+    let r: ParserResult<'_, XCode, ()> = Err(ParserError::new(XCode::Nom, span));
+    assert_eq!(r.or_code(XCode::Level1).unwrap_err().code, XCode::Level1);
+
+    let r: ParserResult<'_, XCode, ()> = Err(ParserError::new(XCode::Level2, span));
+    assert_eq!(r.or_code(XCode::Level1).unwrap_err().code, XCode::Level2);
+
+    let r: ParserResult<'_, XCode, u32> = Ok(1);
+    assert_eq!(r.or_code(XCode::Level1).unwrap(), 1);
+}
+
+#[test]
+pub fn test_require() {
+    let span = Span::new("abc");
+
+    let r: ParserResult<'_, XCode, (Span<'_>, u32)> = Ok((span.take_split(1).0, 1));
+    let (rest, v) = r.optional().require(XCode::Level1).unwrap();
+    assert_eq!(*rest.fragment(), "bc");
+    assert_eq!(v, 1);
+
+    let r: ParserResult<'_, XCode, (Span<'_>, u32)> = Err(ParserError::new(XCode::Nom, span));
+    let err = r.optional().require(XCode::Level1).unwrap_err();
+    assert_eq!(err.code, XCode::Level1);
+    assert_eq!(err.span.location_offset(), 0);
+
+    let r: ParserResult<'_, XCode, (Span<'_>, Option<u32>)> =
+        Err(ParserError::new(XCode::Level2, span));
+    assert_eq!(r.require(XCode::Level1).unwrap_err().code, XCode::Level2);
+}
+
+#[test]
+pub fn test_expects_where() {
+    let span = Span::new("abc");
+
+    let mut err = ParserError::new(XCode::Level1, span);
+    err.add_expect(XCode::Nom, span);
+    err.add_expect(XCode::Level2, span);
+    err.add_suggest(XCode::Nom, span);
+    err.add_suggest(XCode::Level3, span);
+
+    let exp = err.expects_where(|c| !c.is_special());
+    assert_eq!(exp.len(), 1);
+    assert_eq!(exp[0].code, XCode::Level2);
+
+    let sug = err.suggests_where(|c| !c.is_special());
+    assert_eq!(sug.len(), 1);
+    assert_eq!(sug[0].code, XCode::Level3);
+
+    assert_eq!(err.expect_as_ref().len(), 2);
+}
+
+#[test]
+pub fn test_suggest_edits() {
+    let span = Span::new("abc");
+    let (rest, _) = span.take_split(2);
+
+    let mut err = ParserError::new(YCode::Nom, span);
+    err.add_suggest(YCode::Sub, rest);
+    err.add_suggest(YCode::Nom, rest);
+    err.add_suggest(YCode::Sub, span);
+    err.add_suggest(YCode::Nom, span.take(1));
+
+    // Nom has no literal, there is nothing to insert.
+    let edits = err.suggest_edits();
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0].0, 2);
+    assert_eq!(edits[0].1.len(), 1);
+    assert_eq!(edits[0].1[0].range, 2..2);
+    assert_eq!(edits[0].1[0].new_text, ")");
+    assert_eq!(edits[1].0, 0);
+    assert_eq!(edits[1].1[0].new_text, ")");
+
+    let mut err = ParserError::new(YCode::Nom, span);
+    err.add_suggest(YCode::Nom, rest);
+    assert!(err.suggest_as_ref()[0].as_edit().is_none());
+    assert!(err.suggest_edits().is_empty());
+}
+
+#[test]
+pub fn test_accessors() {
+    let span = Span::new("abc");
+
+    let mut err = ParserError::new(XCode::Level1, span.take(2));
+    err.add_expect(XCode::Level2, span);
+
+    assert_eq!(err.code(), XCode::Level1);
+    assert_eq!(*err.span().fragment(), "ab");
+    assert_eq!(err.hints().len(), 1);
+    assert!(matches!(err.hints()[0], Hints::Expect(_)));
+}
+
+#[test]
+pub fn test_expect_hash() {
+    let span = Span::new("abc");
+
+    let mut err = ParserError::new(XCode::Level1, span);
+    err.add_expect(XCode::Level2, span);
+    err.add_expect(XCode::Level2, span.take(2));
+    err.add_expect(XCode::Level2, span.take_split(1).0);
+    err.add_expect(XCode::Level3, span);
+    err.add_suggest(XCode::Level2, span);
+    err.add_suggest(XCode::Level2, span);
+
+    let exp: HashSet<_> = err.expect_as_ref().into_iter().collect();
+    assert_eq!(exp.len(), 3);
+    let sug: HashSet<_> = err.suggest_as_ref().into_iter().collect();
+    assert_eq!(sug.len(), 1);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum YCode {
+    Nom,
+    Sub,
+}
+
+impl Code for YCode {
+    const NOM_ERROR: Self = Self::Nom;
+    const NOM_FAILURE: Self = Self::Nom;
+    const PARSE_INCOMPLETE: Self = Self::Nom;
+
+    fn literal(&self) -> Option<&'static str> {
+        match self {
+            YCode::Sub => Some(")"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for YCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[test]
+pub fn test_map_code() {
+    let span = Span::new("abc");
+
+    let mut err = ParserError::new(XCode::Level1, span.take(1)).into_failure();
+    err.add_expect(XCode::Level2, span);
+    err.add_suggest(XCode::Nom, span);
+
+    let err: ParserError<'_, YCode> = err.map_code(|c| match c {
+        XCode::Nom => YCode::Nom,
+        _ => YCode::Sub,
+    });
+    assert_eq!(err.code, YCode::Sub);
+    assert_eq!(err.span.len(), 1);
+    assert!(err.is_failure());
+    assert_eq!(err.expect_as_ref()[0].code, YCode::Sub);
+    assert_eq!(err.suggest_as_ref()[0].code, YCode::Nom);
+}
+
+#[test]
+pub fn test_literal() {
+    let span = Span::new("(abc");
+    let (rest, _) = span.take_split(4);
+
+    let mut err = ParserError::new(YCode::Nom, rest);
+    err.add_expect(YCode::Sub, rest);
+    err.add_expect(YCode::Nom, rest);
+    err.add_suggest(YCode::Sub, rest);
+    assert_eq!(err.message(), "Nom: expected `)`, Nom");
+
+    let edit = err.suggest_as_ref()[0].as_edit().unwrap();
+    assert_eq!(edit.range, 4..4);
+    assert_eq!(edit.new_text, ")");
+}
+
+#[test]
+pub fn test_seq() {
+    let span = Span::new("abc");
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.enter(XCode::Level1, span);
+    trace.expect(XCode::Level2, span);
+    trace.expect(XCode::Level3, span);
+    trace.suggest(XCode::Level3, span);
+    let err = trace
+        .err::<()>(ParserError::new(XCode::Level1, span))
+        .unwrap_err();
+
+    let seq: Vec<_> = err
+        .expect_as_ref()
+        .iter()
+        .map(|v| (v.code, v.seq))
+        .collect();
+    assert_eq!(seq, vec![(XCode::Level2, 1), (XCode::Level3, 2)]);
+    assert_eq!(err.suggest_as_ref()[0].seq, 3);
+
+    let grp = Expect::group_by_offset(err.expect_as_ref());
+    let codes: Vec<_> = grp[0].1.iter().map(|v| v.code).collect();
+    assert_eq!(codes, vec![XCode::Level3, XCode::Level2]);
+}
+
+#[test]
+pub fn test_nom_span() {
+    let span = Span::new("abc");
+    let (rest, _) = span.take_split(2);
+
+    let err = ParserError::new(XCode::Level1, span);
+    assert_eq!(err.nom_span(), None);
+
+    let err: ParserError<'_, XCode> =
+        ParserError::new_with_nom(XCode::Nom, nom::error::ErrorKind::Digit, rest);
+    let err = nom::error::ParseError::append(span, nom::error::ErrorKind::Tag, err);
+    let mut err = err.into_code(XCode::Level2);
+    err.span = span;
+
+    assert_eq!(err.nom_span().map(|v| v.location_offset()), Some(2));
+}
+
+#[test]
+pub fn test_sort_diagnostics() {
+    let span = Span::new("abc");
+    let (rest, _) = span.take_split(2);
+
+    let mut errors = vec![
+        ParserError::new(XCode::Level1, rest),
+        ParserError::new(XCode::Level3, span),
+        ParserError::new(XCode::Level2, span),
+    ];
+    sort_diagnostics(&mut errors, |c| c as u32);
+
+    let sorted: Vec<_> = errors
+        .iter()
+        .map(|v| (v.span.location_offset(), v.code))
+        .collect();
+    assert_eq!(
+        sorted,
+        vec![(0, XCode::Level2), (0, XCode::Level3), (2, XCode::Level1)]
+    );
+}
+
+#[test]
+pub fn test_to_jsonl() {
+    let span = Span::new("ab\ncd");
+    let (rest, _) = span.take_split(4);
+
+    let mut err = ParserError::new(XCode::Level1, rest);
+    assert_eq!(
+        err.to_jsonl("dir\\a \"b\".txt"),
+        r#"{"file":"dir\\a \"b\".txt","line":2,"col":2,"code":"Level1","message":"Level1"}"#
+    );
+
+    err.add_expect(XCode::Level2, rest);
+    err.add_expect(XCode::Level3, rest);
+    assert_eq!(err.message(), "Level1: expected Level2, Level3");
+    assert!(!err.to_jsonl("x").contains('\n'));
+}
+
+#[test]
+pub fn test_into_iter() {
+    let span = Span::new("abc");
+
+    let mut err = ParserError::new_with_nom(XCode::Level1, nom::error::ErrorKind::Digit, span);
+    err.add_expect(XCode::Level2, span);
+    err.add_suggest(XCode::Level3, span);
+
+    let code = err.code;
+    let hints: Vec<_> = err
+        .into_iter()
+        .map(|v| match v {
+            Hints::Nom(_) => "nom".to_string(),
+            Hints::Expect(v) => format!("expect {}", v.code),
+            Hints::Suggest(v) => format!("suggest {}", v.code),
+            Hints::Trivia(_) => "trivia".to_string(),
+        })
+        .collect();
+
+    assert_eq!(code, XCode::Level1);
+    assert_eq!(hints, vec!["nom", "expect Level2", "suggest Level3"]);
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::XCode;
+use iparse::input::{parse_reader, ChunkError, ChunkedInput};
+use iparse::{ParserNomResult, ParserResult, Span};
+use nom::bytes::streaming::tag;
+use nom::character::complete;
+use nom::character::streaming::{digit1, multispace0};
+use nom::sequence::{delimited, preceded};
+use std::io::Cursor;
+
+// [ number ] with whitespace and line breaks anywhere.
+fn parse_item(
+    rest: Span<'_>,
+    eof: bool,
+) -> ParserResult<'_, XCode, (Span<'_>, Option<(u32, usize)>)> {
+    if eof {
+        // trailing whitespace
+        let (rest2, _) = complete::multispace0::<_, nom::error::Error<_>>(rest).unwrap();
+        if rest2.is_empty() {
+            return Ok((rest2, None));
+        }
+    }
+
+    let r: ParserNomResult<'_, XCode> = delimited(
+        preceded(multispace0, tag("[")),
+        preceded(multispace0, digit1),
+        preceded(multispace0, tag("]")),
+    )(rest);
+    let (rest, tok) = r?;
+    let (rest, _) = multispace0::<_, nom::error::Error<_>>(rest).unwrap_or((rest, rest));
+    Ok((rest, Some((tok.parse().unwrap(), tok.location_offset()))))
+}
+
+fn parse_items(txt: &str) -> Result<Vec<(u32, usize)>, ChunkError<XCode>> {
+    let mut items = Vec::new();
+    parse_reader(Cursor::new(txt), parse_item, |base, v| {
+        if let Some((v, offset)) = v {
+            items.push((v, base + offset))
+        }
+    })?;
+    Ok(items)
+}
+
+#[test]
+pub fn test_parse_reader() {
+    let items = parse_items("[1] [22]\n[\n333\n]\n [4]").unwrap();
+    assert_eq!(items, vec![(1, 1), (22, 5), (333, 11), (4, 19)]);
+
+    // trailing line breaks
+    assert_eq!(parse_items("[1]\n").unwrap(), vec![(1, 1)]);
+    assert_eq!(parse_items("[1]\n[2]\n").unwrap(), vec![(1, 1), (2, 5)]);
+    assert_eq!(parse_items("[1]\n\n  \n").unwrap(), vec![(1, 1)]);
+    assert_eq!(parse_items("\n").unwrap(), vec![]);
+    assert_eq!(parse_items("").unwrap(), vec![]);
+
+    let txt = "[1]\n[2\n";
+    let mut items = Vec::new();
+    let err = parse_reader(Cursor::new(txt), parse_item, |base, v| {
+        if let Some((v, offset)) = v {
+            items.push((v, base + offset))
+        }
+    })
+    .unwrap_err();
+    assert_eq!(items, vec![(1, 1)]);
+    match err {
+        ChunkError::Parse(e) => assert_eq!(e.code, XCode::Incomplete),
+        ChunkError::Io(_) => unreachable!(),
+    }
+
+    let txt = "[1]\nx\n";
+    let err = parse_reader(Cursor::new(txt), parse_item, |_, _| {}).unwrap_err();
+    match err {
+        ChunkError::Parse(e) => {
+            assert_eq!(e.code, XCode::Nom);
+            assert_eq!(e.offset, 4);
+            assert_eq!(e.line, 2);
+        }
+        ChunkError::Io(_) => unreachable!(),
+    }
+}
+
+#[test]
+pub fn test_chunked_input() {
+    let mut input = ChunkedInput::new(Cursor::new("ab\n\u{e4}c\n"));
+    assert!(input.fill().unwrap());
+    assert!(input.fill().unwrap());
+    assert_eq!(*input.span().fragment(), "ab\n\u{e4}c\n");
+
+    input.consume(3).unwrap();
+    assert_eq!((input.offset(), input.line()), (3, 2));
+    assert_eq!(input.span().location_offset(), 0);
+    assert_eq!(input.span().get_column(), 1);
+
+    // inside the umlaut
+    assert!(input.consume(1).is_err());
+    assert!(input.consume(100).is_err());
+    assert_eq!(*input.span().fragment(), "\u{e4}c\n");
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "lsp-types")]
+
+mod common;
+
+use common::XCode;
+use iparse::error::ParserError;
+use iparse::lsp::{to_lsp_position, to_lsp_range, to_lsp_text_edit};
+use iparse::Span;
+use nom::InputTake;
+
+#[test]
+pub fn test_position() {
+    let src = "ab\n\u{1F600}x\ny";
+    assert_eq!(to_lsp_position(0, src).line, 0);
+    // emoji is 4 bytes in utf8 and 2 code units in utf16
+    let pos = to_lsp_position(7, src);
+    assert_eq!((pos.line, pos.character), (1, 2));
+    let pos = to_lsp_position(9, src);
+    assert_eq!((pos.line, pos.character), (2, 0));
+}
+
+#[test]
+pub fn test_diagnostic() {
+    let src = "ab\n\u{1F600}x\ny";
+    let span = Span::new(src);
+    let (rest, _) = span.take_split(7);
+    let (_, x) = rest.take_split(1);
+
+    let range = to_lsp_range(x, src);
+    assert_eq!((range.start.line, range.start.character), (1, 2));
+    assert_eq!((range.end.line, range.end.character), (1, 3));
+
+    let mut err = ParserError::new(XCode::Nom, x);
+    err.add_expect(XCode::Number, x);
+    let diag = err.lsp_diagnostic(src);
+    assert_eq!(diag.message, "Nom: expected Number");
+    assert_eq!(diag.range, range);
+}
+
+#[test]
+pub fn test_text_edit() {
+    let src = "ab\n\u{1F600}x\ny";
+    let span = Span::new(src);
+    let (rest, _) = span.take_split(7);
+
+    let mut err = ParserError::new(XCode::Nom, rest);
+    err.add_suggest(XCode::Semicolon, rest);
+    let edits = err.suggest_edits();
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].0, 7);
+
+    let edit = to_lsp_text_edit(&edits[0].1[0], src);
+    assert_eq!((edit.range.start.line, edit.range.start.character), (1, 2));
+    assert_eq!(edit.range.start, edit.range.end);
+    assert_eq!(edit.new_text, ";");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::XCode;
+use iparse::error::ParserError;
+use iparse::synctracer::SyncTracer;
+use iparse::tracer::CTracer;
+use iparse::{Parser, ParserResult, Span, Tracer};
+use std::thread;
+
+struct ParseSection;
+
+impl<'s> Parser<'s, (), XCode> for ParseSection {
+    fn id() -> XCode {
+        XCode::Section
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.step("section", rest);
+        if rest.fragment().starts_with("section") {
+            trace.ok(rest, rest, ())
+        } else {
+            trace.err(ParserError::new(Self::id(), rest))
+        }
+    }
+}
+
+#[test]
+pub fn parallel() {
+    let input = ["section 1", "noise 2", "section 3"];
+    let sync: SyncTracer<'_, XCode, usize> = SyncTracer::new();
+
+    let mut results = thread::scope(|s| {
+        let workers: Vec<_> = input
+            .iter()
+            .enumerate()
+            .map(|(i, txt)| {
+                let sync = &sync;
+                s.spawn(move || {
+                    let mut trace: CTracer<'_, XCode> = CTracer::new();
+                    let result = ParseSection::parse(&mut trace, Span::new(txt));
+                    sync.merge(i, trace);
+                    (i, result)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|v| v.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+
+    let mut sections = sync.into_sections();
+    sections.sort_by_key(|(i, _)| *i);
+    assert_eq!(sections.len(), 3);
+
+    // Same results and traces as parsing on this thread.
+    for ((i, result), (_, trace)) in results.iter().zip(sections.iter()) {
+        let mut local: CTracer<'_, XCode> = CTracer::new();
+        let local_result = ParseSection::parse(&mut local, Span::new(input[*i]));
+
+        match (result, &local_result) {
+            (Ok((rest, _)), Ok((local_rest, _))) => assert_eq!(rest, local_rest),
+            (Err(e), Err(local_e)) => assert_eq!(e, local_e),
+            _ => panic!("different results for {}", input[*i]),
+        }
+        assert_eq!(trace.tracks().len(), local.tracks().len());
+        assert_eq!(
+            trace.to_tree().map(|v| (v.func, v.outcome)),
+            local.to_tree().map(|v| (v.func, v.outcome))
+        );
+    }
+    assert!(results[1].1.is_err());
+}
@@ -0,0 +1,844 @@
+mod common;
+
+use common::XCode;
+use iparse::error::{DebugWidth, ParserError};
+use iparse::etracer::ErrorTracer;
+use iparse::input::ParseInput;
+use iparse::notracer::NoTracer;
+use iparse::rtracer::RTracer;
+use iparse::stracer::StreamTracer;
+use iparse::test::{test_parse, test_parse_file, CheckDump, CheckTrace, MarkdownTrace, TailTrace};
+use iparse::tracer::{CTracer, TraceOutcome, TraceStyle, Track};
+use iparse::{Parser, ParserResult, Span, StatefulParser, Tracer, TrackParseResult};
+use nom::character::complete::multispace0;
+use nom::InputTake;
+use std::{env, fs, process};
+
+struct ParseStatement;
+
+impl<'s> Parser<'s, (), XCode> for ParseStatement {
+    fn id() -> XCode {
+        XCode::Statement
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.suggest(XCode::Keyword, rest);
+        trace.ok(rest, rest, ())
+    }
+}
+
+#[test]
+pub fn test_keep_suggest_on_ok() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    ParseStatement::parse(&mut trace, Span::new("x")).unwrap();
+    assert_eq!(trace.to_suggest().len(), 1);
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.set_keep_suggest_on_ok(false);
+    ParseStatement::parse(&mut trace, Span::new("x")).unwrap();
+    assert!(trace.to_suggest().is_empty());
+}
+
+struct ParseChar;
+
+impl<'s> Parser<'s, (), XCode> for ParseChar {
+    fn id() -> XCode {
+        XCode::Char
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, tok) = rest.take_split(1);
+        trace.ok(rest, tok, ())
+    }
+}
+
+#[test]
+pub fn test_parse_complete() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    assert!(ParseChar::parse_complete(&mut trace, Span::new("x")).is_ok());
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParseChar::parse_complete(&mut trace, Span::new("xyz")).unwrap_err();
+    assert_eq!(err.code, XCode::Incomplete);
+    assert_eq!(err.span.location_offset(), 1);
+    assert_eq!(*err.span.fragment(), "yz");
+}
+
+fn parse_word<'s>(
+    trace: &mut CTracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, Span<'s>)> {
+    let rest = trace.enter_skip_trivia(XCode::Keyword, rest);
+    let (rest, tok) = rest.take_split(3);
+    trace.ok(rest, tok, tok)
+}
+
+#[test]
+pub fn test_trivia() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new().with_trivia(multispace0);
+    let (rest, tok) = parse_word(&mut trace, Span::new("  \tlet x")).unwrap();
+    assert_eq!(*tok.fragment(), "let");
+    assert_eq!(*rest.fragment(), " x");
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (_, tok) = parse_word(&mut trace, Span::new("let x")).unwrap();
+    assert_eq!(*tok.fragment(), "let");
+}
+
+#[test]
+pub fn test_parse_input() {
+    let input = ParseInput::new(format!("{}{}", "x", "yz"));
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let (rest, _) = ParseChar::parse(&mut trace, input.span()).unwrap();
+    assert_eq!(*rest.fragment(), "yz");
+
+    test_parse(&input, ParseChar::parse)
+        .okok()
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+pub fn test_file() {
+    let path = env::temp_dir().join(format!("iparse_test_file_{}.txt", process::id()));
+    fs::write(&path, "xyz").unwrap();
+
+    test_parse_file(&path, ParseChar::parse)
+        .okok()
+        .rest("yz")
+        .q(&CheckTrace(DebugWidth::Short));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "can't read")]
+pub fn test_file_missing() {
+    let path = env::temp_dir().join("iparse_test_file_missing.txt");
+    let _ = test_parse_file(&path, ParseChar::parse);
+}
+
+#[test]
+pub fn test_trace_eq() {
+    test_parse("xyz", ParseChar::parse)
+        .trace_eq(
+            r#"trace
+  Char: enter with "xyz"
+  Char: ok matched 1 bytes, rest at 1 "yz"
+    suggest=Char: []
+
+"#,
+        )
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+#[should_panic]
+pub fn test_trace_eq_mismatch() {
+    test_parse("xyz", ParseChar::parse)
+        .trace_eq(
+            r#"trace
+  Char: enter with "xyz"
+  Char: err
+"#,
+        )
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+pub fn test_debug() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    ParseChar::parse(&mut trace, Span::new("x")).unwrap();
+    let txt = format!("{:?}", trace);
+    assert!(txt.starts_with("trace"));
+    assert!(txt.contains("Char"));
+
+    let mut trace: RTracer<'_, XCode> = RTracer::new();
+    ParseChar::parse(&mut trace, Span::new("x")).unwrap();
+    assert!(format!("{:?}", trace).starts_with("trace"));
+}
+
+struct ParsePair;
+
+impl<'s> Parser<'s, (), XCode> for ParsePair {
+    fn id() -> XCode {
+        XCode::Pair
+    }
+
+    fn context() -> Option<XCode> {
+        Some(Self::id())
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, _) = Self::parse_child::<_, ParseChar>(trace, rest)?;
+        let (rest, _) = Self::parse_child::<_, ParseFail>(trace, rest)?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+struct ParseFail;
+
+impl<'s> Parser<'s, (), XCode> for ParseFail {
+    fn id() -> XCode {
+        XCode::Keyword
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.err(ParserError::new(Self::id(), rest))
+    }
+}
+
+#[test]
+pub fn test_context() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParsePair::parse(&mut trace, Span::new("xy")).unwrap_err();
+    assert_eq!(err.code, XCode::Pair);
+    assert!(err.is_expected2(XCode::Keyword, XCode::Pair));
+}
+
+struct ParseNoEnter;
+
+impl<'s> Parser<'s, (), XCode> for ParseNoEnter {
+    fn id() -> XCode {
+        XCode::Pair
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        let (rest, _) = ParseChar::parse(trace, rest).track(trace)?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "without trace.enter()")]
+pub fn test_missing_enter() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParseNoEnter::parse(&mut trace, Span::new("xy"));
+}
+
+struct ParseNoEnterFail;
+
+impl<'s> Parser<'s, (), XCode> for ParseNoEnterFail {
+    fn id() -> XCode {
+        XCode::Pair
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        let (rest, _) = ParseFail::parse(trace, rest).track(trace)?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+struct ParseOuter;
+
+impl<'s> Parser<'s, (), XCode> for ParseOuter {
+    fn id() -> XCode {
+        XCode::Statement
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, _) = ParseNoEnterFail::parse(trace, rest).track(trace)?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "without trace.enter()")]
+pub fn test_missing_enter_track() {
+    // ParseNoEnterFail finishes the frame of ParseOuter with track().
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParseOuter::parse(&mut trace, Span::new("xy"));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "unfinished parser functions")]
+pub fn test_unbalanced_drop() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.enter(XCode::Pair, Span::new("x"));
+}
+
+#[test]
+pub fn test_on_error() {
+    let mut codes = Vec::new();
+    {
+        let mut trace: CTracer<'_, XCode> = CTracer::new();
+        trace.on_error(|e| codes.push(e.code));
+        let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    }
+    assert_eq!(codes, vec![XCode::Keyword, XCode::Pair]);
+}
+
+#[test]
+pub fn test_enter_counts() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    let _ = ParseChar::parse(&mut trace, Span::new("x"));
+    assert_eq!(
+        trace.enter_counts(),
+        vec![(XCode::Pair, 1), (XCode::Char, 2), (XCode::Keyword, 1)]
+    );
+
+    let mut trace: CTracer<'_, XCode, false> = CTracer::new();
+    let _ = ParseChar::parse(&mut trace, Span::new("x"));
+    assert!(trace.enter_counts().is_empty());
+}
+
+#[test]
+pub fn test_consumed_range() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    let ranges: Vec<_> = trace
+        .tracks()
+        .iter()
+        .filter_map(|v| match v {
+            Track::Ok(v) => Some((v.func, v.consumed_range())),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(ranges, vec![(XCode::Char, 0..1)]);
+}
+
+#[test]
+pub fn test_with_capacity() {
+    let mut trace: CTracer<'_, XCode> = CTracer::with_capacity(1000);
+    assert!(trace.track_capacity() >= 1000);
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    assert_eq!(trace.enter_counts()[0], (XCode::Pair, 1));
+
+    let trace: CTracer<'_, XCode, false> = CTracer::with_capacity(1000);
+    assert_eq!(trace.track_capacity(), 0);
+}
+
+#[test]
+pub fn test_enter_count_map() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    let _ = ParseChar::parse(&mut trace, Span::new("x"));
+    let counts = trace.enter_count_map();
+    assert_eq!(counts.len(), 3);
+    assert_eq!(counts[&XCode::Char], 2);
+    assert_eq!(counts[&XCode::Pair], 1);
+}
+
+#[test]
+pub fn test_stream() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    let mut expected = String::new();
+    trace
+        .write(&mut expected, DebugWidth::Medium, &|_| true)
+        .unwrap();
+
+    let mut stream: StreamTracer<'_, XCode, Vec<u8>> =
+        StreamTracer::with_writer(Vec::new(), DebugWidth::Medium);
+    let err = ParsePair::parse(&mut stream, Span::new("xy")).unwrap_err();
+    assert_eq!(err.code, XCode::Pair);
+    assert!(stream.io_error().is_none());
+
+    let out = String::from_utf8(stream.into_writer().unwrap()).unwrap();
+    assert_eq!(format!("trace\n{}", out), expected);
+}
+
+struct ParseNoted;
+
+impl<'s> Parser<'s, (), XCode> for ParseNoted {
+    fn id() -> XCode {
+        XCode::Char
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.step("first", rest);
+        trace.note("rule", "7".to_string());
+        trace.ok(rest, rest, ())
+    }
+}
+
+#[test]
+pub fn test_note() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    ParseNoted::parse(&mut trace, Span::new("x")).unwrap();
+    let txt = format!("{:?}", trace);
+    assert!(txt.contains("\n  Char: step first \"x\"\n  Char: note rule=7\n"));
+}
+
+struct ParseRecovered;
+
+impl<'s> Parser<'s, (), XCode> for ParseRecovered {
+    fn id() -> XCode {
+        XCode::Statement
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, token) = rest.take_split(1);
+        trace.synthetic(XCode::Keyword, token);
+        trace.ok(rest, token, ())
+    }
+}
+
+#[test]
+pub fn test_synthetic() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    ParseRecovered::parse(&mut trace, Span::new("xy")).unwrap();
+    let txt = format!("{:?}", trace);
+    assert!(txt.contains("\n  Statement: synthetic Keyword \"x\"\n"));
+    assert_eq!(trace.synthetic_tokens().len(), 1);
+    assert!(trace.is_synthetic(Span::new("xy").take(1)));
+    assert!(!trace.is_synthetic(Span::new("xy")));
+
+    let mut trace: CTracer<'_, XCode, false> = CTracer::new();
+    ParseRecovered::parse(&mut trace, Span::new("xy")).unwrap();
+    assert_eq!(trace.synthetic_tokens()[0].0, XCode::Keyword);
+
+    let mut trace = NoTracer::new();
+    ParseRecovered::parse(&mut trace, Span::new("xy")).unwrap();
+}
+
+#[test]
+pub fn test_markdown() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+    let mut md = String::new();
+    trace
+        .write_markdown(&mut md, DebugWidth::Short, &|_| true)
+        .unwrap();
+
+    let lines: Vec<_> = md.lines().collect();
+    assert_eq!(lines[0], "- **Pair** `xy`");
+    assert_eq!(lines[1], "  - **Char** `xy`");
+    assert_eq!(lines[2], "    - ok `x` rest `y`");
+    assert_eq!(lines[3], "  - **Keyword** `y`");
+    assert!(lines
+        .last()
+        .unwrap()
+        .starts_with("  - err (backtracked 1 bytes) Pair"));
+
+    test_parse("xy", ParsePair::parse).q(&MarkdownTrace(DebugWidth::Short));
+}
+
+struct ParseCurrent;
+
+impl<'s> Parser<'s, Option<XCode>, XCode> for ParseCurrent {
+    fn id() -> XCode {
+        XCode::Keyword
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, Option<XCode>)> {
+        trace.enter(Self::id(), rest);
+        let current = trace.current();
+        trace.ok(rest, rest, current)
+    }
+}
+
+fn current_via<'s>(mut trace: impl Tracer<'s, XCode>) -> Option<XCode> {
+    assert_eq!(trace.current(), None);
+    let (_, current) = ParseCurrent::parse(&mut trace, Span::new("x")).unwrap();
+    assert_eq!(trace.current(), None);
+    current
+}
+
+#[test]
+pub fn test_current() {
+    assert_eq!(
+        current_via(CTracer::<'_, XCode>::new()),
+        Some(XCode::Keyword)
+    );
+    assert_eq!(
+        current_via(RTracer::<'_, XCode>::new()),
+        Some(XCode::Keyword)
+    );
+    assert_eq!(
+        current_via(ErrorTracer::<'_, XCode>::new()),
+        Some(XCode::Keyword)
+    );
+    assert_eq!(current_via(NoTracer::<'_, XCode>::new()), None);
+}
+
+#[test]
+pub fn test_span_so_far() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let span = Span::new("keyword rest");
+    assert!(trace.span_so_far(span).is_none());
+
+    trace.enter(XCode::Pair, span);
+    let (rest, _) = span.take_split(7);
+    trace.enter(XCode::Keyword, rest);
+    assert_eq!(*trace.span_so_far(rest).unwrap(), "");
+    // rest before the start of the current function.
+    assert!(trace.span_so_far(span).is_none());
+    let _ = trace.ok(rest, rest.take(0), ());
+
+    let so_far = trace.span_so_far(rest).unwrap();
+    assert_eq!(*so_far, "keyword");
+    assert_eq!(so_far.location_offset(), 0);
+    // rest from some other input, beyond the end.
+    let (other, _) = Span::new("some other longer text").take_split(15);
+    assert!(trace.span_so_far(other).is_none());
+    let _ = trace.ok(rest, so_far, ());
+}
+
+#[test]
+pub fn test_to_tree() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    assert!(trace.to_tree().is_none());
+
+    let _ = ParsePair::parse(&mut trace, Span::new("ab"));
+    let tree = trace.to_tree().unwrap();
+    assert_eq!(tree.func, XCode::Pair);
+    assert_eq!(tree.outcome, TraceOutcome::Err);
+    assert_eq!(*tree.span.fragment(), "ab");
+
+    let children: Vec<_> = tree.children.iter().map(|v| (v.func, v.outcome)).collect();
+    assert_eq!(
+        children,
+        vec![
+            (XCode::Char, TraceOutcome::Ok),
+            (XCode::Keyword, TraceOutcome::Err)
+        ]
+    );
+    assert_eq!(tree.children[1].span.location_offset(), 1);
+}
+
+#[test]
+pub fn test_trace_style() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("ab"));
+
+    let mut plain = String::new();
+    trace
+        .write(&mut plain, DebugWidth::Medium, &|_| true)
+        .unwrap();
+    let mut styled = String::new();
+    trace
+        .write_styled(
+            &mut styled,
+            DebugWidth::Medium,
+            TraceStyle::default(),
+            &|_| true,
+        )
+        .unwrap();
+    assert_eq!(plain, styled);
+
+    let mut styled = String::new();
+    trace
+        .write_styled(
+            &mut styled,
+            DebugWidth::Medium,
+            TraceStyle {
+                indent: 4,
+                color: true,
+            },
+            &|_| true,
+        )
+        .unwrap();
+    assert!(styled.contains("\n    \x1b[32mPair: enter with \"ab\"\x1b[0m\n"));
+    assert!(styled.contains("\x1b[31mKeyword: err "));
+    assert!(styled.contains("\n        Char: ok"));
+}
+
+#[test]
+pub fn test_error_stack() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = ParsePair::parse(&mut trace, Span::new("ab")).unwrap_err();
+    assert_eq!(err.stack, vec![XCode::Pair, XCode::Keyword]);
+
+    let mut trace: CTracer<'_, XCode, false> = CTracer::new();
+    let err = ParsePair::parse(&mut trace, Span::new("ab")).unwrap_err();
+    assert!(err.stack.is_empty());
+
+    let mut trace: NoTracer<'_, XCode> = NoTracer::new();
+    let err = ParsePair::parse(&mut trace, Span::new("ab")).unwrap_err();
+    assert!(err.stack.is_empty());
+}
+
+#[test]
+pub fn test_entered() {
+    test_parse("ab", ParsePair::parse)
+        .entered(XCode::Char)
+        .entered(XCode::Keyword)
+        .q(&CheckDump(DebugWidth::Short));
+}
+
+#[test]
+#[should_panic(expected = "test failed")]
+pub fn test_entered_fail() {
+    test_parse("ab", ParsePair::parse)
+        .entered(XCode::Statement)
+        .q(&CheckTrace(DebugWidth::Short));
+}
+
+#[test]
+pub fn test_backtracked() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("ab"));
+
+    let errs: Vec<_> = trace
+        .tracks()
+        .iter()
+        .filter_map(|v| match v {
+            Track::Err(v) => Some((v.func, v.backtracked, v.consumed)),
+            _ => None,
+        })
+        .collect();
+    // Keyword fails where it started, Pair after consuming the Char.
+    assert_eq!(
+        errs,
+        vec![(XCode::Keyword, false, 0), (XCode::Pair, true, 1)]
+    );
+
+    let mut buf = String::new();
+    trace.write(&mut buf, DebugWidth::Short, &|_| true).unwrap();
+    assert!(buf.contains("P: err (backtracked 1 bytes) "));
+}
+
+#[test]
+pub fn test_short_name() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("xy"));
+
+    let mut buf = String::new();
+    trace.write(&mut buf, DebugWidth::Short, &|_| true).unwrap();
+    assert!(buf.contains("P: enter with \"xy\""));
+    assert!(buf.contains("Char: enter with \"xy\""));
+
+    let mut buf = String::new();
+    trace
+        .write(&mut buf, DebugWidth::Medium, &|_| true)
+        .unwrap();
+    assert!(buf.contains("Pair: enter with \"xy\""));
+}
+
+struct ParseDecl;
+
+impl<'s> StatefulParser<'s, Vec<String>, (), XCode> for ParseDecl {
+    fn id() -> XCode {
+        XCode::Keyword
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        state: &mut Vec<String>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, tok) = rest.take_split(1);
+        if state.iter().any(|v| v == *tok.fragment()) {
+            return trace.err(ParserError::new(Self::id(), tok));
+        }
+        state.push(tok.to_string());
+        trace.ok(rest, tok, ())
+    }
+}
+
+#[test]
+pub fn test_stateful() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let mut state = Vec::new();
+
+    let rest = Span::new("aba");
+    let (rest, _) = ParseDecl::parse(&mut trace, &mut state, rest).unwrap();
+    let (rest, _) = ParseDecl::parse(&mut trace, &mut state, rest).unwrap();
+    let err = ParseDecl::parse(&mut trace, &mut state, rest).unwrap_err();
+
+    assert_eq!(state, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(err.span.location_offset(), 2);
+}
+
+#[test]
+pub fn test_tail_tracks() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, Span::new("ab"));
+
+    let all = trace.tracks().len();
+    assert_eq!(trace.tail_tracks(1000).len(), all);
+    assert_eq!(trace.tail_tracks(2).len(), 2);
+    assert!(matches!(trace.tail_tracks(2)[1], Track::Exit(_)));
+
+    let mut full = String::new();
+    trace
+        .write(&mut full, DebugWidth::Medium, &|_| true)
+        .unwrap();
+    let mut tail = String::new();
+    trace.write_tail(&mut tail, DebugWidth::Medium, 4).unwrap();
+
+    // same lines and indentation as the full trace.
+    let full: Vec<_> = full.lines().collect();
+    let tail: Vec<_> = tail.lines().collect();
+    assert_eq!(tail[0], format!("trace ({} tracks skipped)", all - 4));
+    for line in &tail[1..] {
+        assert!(full.contains(line));
+    }
+
+    test_parse("ab", ParsePair::parse).q(&TailTrace(4));
+}
+
+#[test]
+pub fn test_high_water() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    assert_eq!(trace.high_water(), 0);
+
+    // Pair reads one char and fails with Keyword at offset 1.
+    let err = ParsePair::parse(&mut trace, Span::new("abc")).unwrap_err();
+    assert_eq!(err.code, XCode::Pair);
+    assert_eq!(trace.high_water(), 1);
+
+    // The high water mark stays when a later parse fails earlier.
+    let _ = ParseFail::parse(&mut trace, Span::new("abc"));
+    assert_eq!(trace.high_water(), 1);
+}
+
+#[test]
+pub fn test_progress() {
+    let mut seen = Vec::new();
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.on_progress(|v| seen.push(v));
+
+    let rest = Span::new("xyz");
+    let (rest, _) = ParseChar::parse(&mut trace, rest).unwrap();
+    let (rest, _) = ParseChar::parse(&mut trace, rest).unwrap();
+    // No progress, no callback.
+    let _ = ParseFail::parse(&mut trace, rest);
+    let _ = ParseChar::parse(&mut trace, rest).unwrap();
+    assert_eq!(trace.progress(), 3);
+    drop(trace);
+
+    assert_eq!(seen, vec![1, 2, 3]);
+}
+
+#[test]
+pub fn test_stall() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.set_stall_limit(5);
+
+    let span = Span::new("ab");
+    for _ in 0..10 {
+        let _ = ParseFail::parse(&mut trace, span);
+    }
+
+    let stalls: Vec<_> = trace
+        .tracks()
+        .iter()
+        .filter_map(|v| match v {
+            Track::Debug(v) => Some(v.dbg.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(stalls, vec!["possible stall in Keyword at 0"]);
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParsePair::parse(&mut trace, span);
+    assert!(!trace.tracks().iter().any(|v| matches!(v, Track::Debug(_))));
+
+    // Pair enters Char at the same offset again and again.
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.set_stall_limit(1);
+    for _ in 0..10 {
+        let _ = ParsePair::parse(&mut trace, span);
+    }
+    let stalls: Vec<_> = trace
+        .tracks()
+        .iter()
+        .filter_map(|v| match v {
+            Track::Debug(v) => Some(v.dbg.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        stalls,
+        vec![
+            "possible stall in Keyword at 1",
+            "possible stall in Pair at 0",
+            "possible stall in Char at 0"
+        ]
+    );
+
+    // Progress resets the count.
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    trace.set_stall_limit(1);
+    let span = Span::new("abcdef");
+    for n in 0..5 {
+        let (rest, _) = span.take_split(n);
+        let _ = ParsePair::parse(&mut trace, rest);
+    }
+    assert!(!trace.tracks().iter().any(|v| matches!(v, Track::Debug(_))));
+}
+
+#[test]
+pub fn test_debug_ok() {
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let _ = ParseChar::parse(&mut trace, Span::new("xyz"));
+
+    let mut buf = String::new();
+    trace
+        .write(&mut buf, DebugWidth::Medium, &|_| true)
+        .unwrap();
+    assert!(buf.contains("Char: ok matched 1 bytes, rest at 1 \"yz\""));
+}
+
+fn parse_word_fail<'s>(
+    trace: &mut impl Tracer<'s, XCode>,
+    rest: Span<'s>,
+) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+    trace.enter(XCode::Keyword, rest);
+    let (rest, ws) = multispace0::<_, nom::error::Error<_>>(rest).unwrap();
+    trace.trivia(ws);
+    trace.err(ParserError::new(XCode::Keyword, rest))
+}
+
+#[test]
+pub fn test_trivia_hint() {
+    let span = Span::new("  x");
+
+    let mut trace: CTracer<'_, XCode> = CTracer::new();
+    let err = parse_word_fail(&mut trace, span).unwrap_err();
+    let trivia: Vec<_> = err.trivia().iter().map(|v| *v.span.fragment()).collect();
+    assert_eq!(trivia, vec!["  "]);
+    assert!(format!("{:2?}", err).contains("trivia=\n    0:\"  \""));
+
+    let mut trace: ErrorTracer<'_, XCode> = ErrorTracer::new();
+    let err = parse_word_fail(&mut trace, span).unwrap_err();
+    assert_eq!(err.trivia().len(), 1);
+
+    let mut trace: NoTracer<'_, XCode> = NoTracer::new();
+    let err = parse_word_fail(&mut trace, span).unwrap_err();
+    assert!(err.trivia().is_empty());
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use common::XCode;
+use iparse::error::ParserError;
+use iparse::ttracer::TracingTracer;
+use iparse::{Parser, ParserResult, Span, Tracer, TrackParseResult};
+use nom::{InputTake, Slice};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Records span enter/exit and the fields of events as text.
+#[derive(Default, Clone)]
+struct Capture {
+    log: Arc<Mutex<Vec<String>>>,
+    funcs: Arc<Mutex<HashMap<u64, String>>>,
+}
+
+#[derive(Default)]
+struct Fields(Vec<String>);
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push(format!("{}={}", field.name(), value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let func = fields
+            .0
+            .iter()
+            .find_map(|v| v.strip_prefix("func="))
+            .unwrap_or_default()
+            .to_string();
+
+        let mut funcs = self.funcs.lock().unwrap();
+        let id = funcs.len() as u64 + 1;
+        funcs.insert(id, func);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        self.log.lock().unwrap().push(fields.0.join(" "));
+    }
+
+    fn enter(&self, span: &Id) {
+        let func = self.funcs.lock().unwrap()[&span.into_u64()].clone();
+        self.log.lock().unwrap().push(format!("enter {}", func));
+    }
+
+    fn exit(&self, span: &Id) {
+        let func = self.funcs.lock().unwrap()[&span.into_u64()].clone();
+        self.log.lock().unwrap().push(format!("exit {}", func));
+    }
+}
+
+struct ParseInner;
+
+impl<'s> Parser<'s, (), XCode> for ParseInner {
+    fn id() -> XCode {
+        XCode::Inner
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        trace.step("inner", rest);
+        trace.err(ParserError::new(Self::id(), rest))
+    }
+}
+
+struct ParseOuter;
+
+impl<'s> Parser<'s, (), XCode> for ParseOuter {
+    fn id() -> XCode {
+        XCode::Outer
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (rest, _) = ParseInner::parse(trace, rest).track_as(trace, Self::id())?;
+        trace.ok(rest, rest, ())
+    }
+}
+
+struct ParseSection;
+
+impl<'s> Parser<'s, (), XCode> for ParseSection {
+    fn id() -> XCode {
+        XCode::Section
+    }
+
+    fn parse<'t>(
+        trace: &'t mut impl Tracer<'s, XCode>,
+        rest: Span<'s>,
+    ) -> ParserResult<'s, XCode, (Span<'s>, ())> {
+        trace.enter(Self::id(), rest);
+        let (tok, rest) = (rest.take(1), rest.slice(1..));
+        trace.ok(rest, tok, ())
+    }
+}
+
+#[test]
+pub fn test_tracing() {
+    let capture = Capture::default();
+    let log = capture.log.clone();
+
+    let err = tracing::subscriber::with_default(capture, || {
+        let mut trace: TracingTracer<'_, XCode> = TracingTracer::new();
+        ParseOuter::parse(&mut trace, Span::new("abc")).unwrap_err()
+    });
+    assert_eq!(err.code, XCode::Outer);
+    assert!(err.is_expected(XCode::Inner));
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "enter Outer",
+            "enter Inner",
+            "step=inner offset=0 input=abc",
+            "err=Inner offset=0",
+            "exit Inner",
+            "err=Outer offset=0",
+            "exit Outer",
+        ]
+    );
+}
+
+#[test]
+pub fn test_tracing_ok() {
+    let capture = Capture::default();
+    let log = capture.log.clone();
+
+    tracing::subscriber::with_default(capture, || {
+        let mut trace: TracingTracer<'_, XCode> = TracingTracer::new();
+        ParseSection::parse(&mut trace, Span::new("abc")).unwrap();
+    });
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["enter Section", "ok=a rest=1", "exit Section"]
+    );
+}